@@ -0,0 +1,60 @@
+use std::collections::{hash_map::Entry, HashMap};
+use std::hash::Hash;
+
+/// Applies `step` to `state` `target` times, skipping whole periods once a key repeats
+pub fn simulate<S, K, FK, FS>(mut state: S, target: usize, mut key: FK, mut step: FS) -> S
+where
+    K: Eq + Hash,
+    FK: FnMut(&S) -> K,
+    FS: FnMut(&mut S),
+{
+    let mut seen = HashMap::new();
+    seen.insert(key(&state), 0);
+
+    let mut iteration = 0;
+    while iteration < target {
+        step(&mut state);
+        iteration += 1;
+
+        match seen.entry(key(&state)) {
+            Entry::Vacant(vac) => {
+                vac.insert(iteration);
+            }
+            Entry::Occupied(occ) => {
+                let period = iteration - occ.get();
+                let remaining = (target - iteration) % period;
+
+                for _ in 0..remaining {
+                    step(&mut state);
+                }
+
+                return state;
+            }
+        }
+    }
+
+    state
+}
+
+#[test]
+fn test_simulate() {
+    // 3 steps of lead-in before entering a cycle of length 5
+    let step = |value: &mut usize| {
+        *value = if *value < 3 {
+            *value + 1
+        } else {
+            3 + (*value - 2) % 5
+        }
+    };
+    let brute_force = |target: usize| {
+        let mut value = 0;
+        for _ in 0..target {
+            step(&mut value);
+        }
+        value
+    };
+
+    for target in [0, 1, 2, 3, 7, 8, 100, 1_001] {
+        assert_eq!(brute_force(target), simulate(0, target, |v| *v, step));
+    }
+}
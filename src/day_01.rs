@@ -1,32 +1,194 @@
+use crate::stream;
 use aoc_runner_derive::aoc;
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
 
 #[aoc(day1, part1)]
 fn solve_part1(input: &str) -> u32 {
-    input
-        .lines()
-        .map(|line| {
-            let mut digits = line.chars().filter_map(|c| c.to_digit(10));
-            let first_digit = digits.next().unwrap();
-            let last_digit = digits.next_back().unwrap_or(first_digit);
-
-            first_digit * 10 + last_digit
-        })
-        .sum()
+    input.lines().map(calibration_value).sum()
 }
 
 #[aoc(day1, part2)]
 fn solve_part2(input: &str) -> usize {
+    let scanner = DigitScanner::english();
     input
         .lines()
-        .map(|line| {
-            let first_digit = match_first_digit(line);
-            let last_digit = match_last_digit(line);
+        .map(|line| scanner.calibration_value(line).unwrap())
+        .sum()
+}
+
+/// Summed as `usize`, since a long enough stream would overflow a `u32`
+pub fn stream_part1<R: BufRead>(reader: R) -> io::Result<usize> {
+    stream::fold_lines(reader, 0, |total, line| {
+        total + calibration_value(line) as usize
+    })
+}
+
+pub fn stream_part2<R: BufRead>(reader: R) -> io::Result<usize> {
+    let scanner = DigitScanner::english();
+    stream::fold_lines(reader, 0, |total, line| {
+        total + scanner.calibration_value(line).unwrap()
+    })
+}
+
+/// An Aho-Corasick automaton over the digit words, so overlapping words like "twone" are all found
+pub struct DigitScanner {
+    nodes: Vec<ScanNode>,
+    words: Vec<(usize, usize)>,
+}
+
+#[derive(Default)]
+struct ScanNode {
+    children: Vec<(u8, usize)>,
+    /// The longest proper suffix of this node that is also in the trie
+    fail: usize,
+    outputs: Vec<usize>,
+}
+
+impl ScanNode {
+    fn child(&self, byte: u8) -> Option<usize> {
+        self.children
+            .iter()
+            .find(|(label, _)| *label == byte)
+            .map(|(_, child)| *child)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DigitMatch {
+    pub start: usize,
+    pub end: usize,
+    pub value: usize,
+    word: usize,
+}
+
+impl DigitScanner {
+    /// When two words start (or end) at the same place, the one given first wins
+    pub fn new<'a, I>(vocabulary: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, usize)>,
+    {
+        let mut nodes = vec![ScanNode::default()];
+        let mut words = Vec::new();
+
+        for (word, value) in vocabulary {
+            assert!(!word.is_empty(), "Digit words must not be empty");
+
+            let mut node = 0;
+            for byte in word.bytes() {
+                node = match nodes[node].child(byte) {
+                    Some(child) => child,
+                    None => {
+                        nodes.push(ScanNode::default());
+                        let child = nodes.len() - 1;
+                        nodes[node].children.push((byte, child));
+                        child
+                    }
+                };
+            }
+
+            nodes[node].outputs.push(words.len());
+            words.push((word.len(), value));
+        }
+
+        // Breadth first, so the failure link of every shallower node is already in place
+        let mut queue: VecDeque<_> = nodes[0].children.iter().map(|(_, child)| *child).collect();
+        while let Some(node) = queue.pop_front() {
+            for (byte, child) in nodes[node].children.clone() {
+                let mut fallback = nodes[node].fail;
+                let fail = loop {
+                    if let Some(next) = nodes[fallback].child(byte) {
+                        break next;
+                    }
+                    if fallback == 0 {
+                        break 0;
+                    }
+                    fallback = nodes[fallback].fail;
+                };
+
+                nodes[child].fail = fail;
+                let inherited = nodes[fail].outputs.clone();
+                nodes[child].outputs.extend(inherited);
+                queue.push_back(child);
+            }
+        }
+
+        Self { nodes, words }
+    }
+
+    pub fn english() -> Self {
+        Self::new(DIGIT_VALUES)
+    }
+
+    fn step(&self, mut node: usize, byte: u8) -> usize {
+        loop {
+            if let Some(next) = self.nodes[node].child(byte) {
+                return next;
+            }
+            if node == 0 {
+                return 0;
+            }
+            node = self.nodes[node].fail;
+        }
+    }
+
+    /// Every word in the line, ordered by where it ends
+    pub fn matches<'a>(&'a self, line: &'a str) -> impl Iterator<Item = DigitMatch> + 'a {
+        let mut node = 0;
+
+        line.bytes().enumerate().flat_map(move |(index, byte)| {
+            node = self.step(node, byte);
 
-            first_digit * 10 + last_digit
+            self.nodes[node].outputs.iter().map(move |word| {
+                let (length, value) = self.words[*word];
+                DigitMatch {
+                    start: index + 1 - length,
+                    end: index + 1,
+                    value,
+                    word: *word,
+                }
+            })
         })
-        .sum()
+    }
+
+    pub fn calibration_value(&self, line: &str) -> Option<usize> {
+        let mut first: Option<DigitMatch> = None;
+        let mut last: Option<DigitMatch> = None;
+
+        for found in self.matches(line) {
+            if first.is_none_or(|first| (found.start, found.word) < (first.start, first.word)) {
+                first = Some(found);
+            }
+            if last.is_none_or(|last| {
+                (found.end, Reverse(found.word)) > (last.end, Reverse(last.word))
+            }) {
+                last = Some(found);
+            }
+        }
+
+        Some(first?.value * 10 + last?.value)
+    }
+}
+
+fn calibration_value(line: &str) -> u32 {
+    let mut digits = line.chars().filter_map(|c| c.to_digit(10));
+    let first_digit = digits.next().unwrap();
+    let last_digit = digits.next_back().unwrap_or(first_digit);
+
+    first_digit * 10 + last_digit
 }
 
+/// Tries every digit word at every prefix and suffix, to check the scanner
+#[cfg(test)]
+fn spelled_calibration_value(line: &str) -> usize {
+    let first_digit = match_first_digit(line);
+    let last_digit = match_last_digit(line);
+
+    first_digit * 10 + last_digit
+}
+
+#[cfg(test)]
 fn match_first_digit(line: &str) -> usize {
     let mut current = line;
 
@@ -40,6 +202,7 @@ fn match_first_digit(line: &str) -> usize {
     unreachable!("No digits found!");
 }
 
+#[cfg(test)]
 fn match_last_digit(line: &str) -> usize {
     let mut current = line;
     while !current.is_empty() {
@@ -52,6 +215,7 @@ fn match_last_digit(line: &str) -> usize {
     unreachable!("No digits found!");
 }
 
+#[cfg(test)]
 fn match_digit(line: &str) -> Option<usize> {
     for (digit, value) in DIGIT_VALUES {
         if line.starts_with(digit) {
@@ -62,6 +226,7 @@ fn match_digit(line: &str) -> Option<usize> {
     None
 }
 
+#[cfg(test)]
 fn match_digit_back(line: &str) -> Option<usize> {
     for (digit, value) in DIGIT_VALUES {
         if line.ends_with(digit) {
@@ -94,3 +259,78 @@ const DIGIT_VALUES: [(&str, usize); 20] = [
     ("nine", 9),
     ("9", 9),
 ];
+
+#[test]
+fn test_streaming() {
+    let part1 = "1abc2\npqr3stu8vwx\na1b2c3d4e5f\ntreb7uchet";
+    let part2 = "two1nine
+eightwothree
+abcone2threexyz
+xtwone3four
+4nineeightseven2
+zoneight234
+7pqrstsixteen";
+
+    assert_eq!(142, solve_part1(part1));
+    assert_eq!(142, stream_part1(part1.as_bytes()).unwrap());
+    assert_eq!(281, solve_part2(part2));
+    assert_eq!(281, stream_part2(part2.as_bytes()).unwrap());
+
+    let reader = io::BufReader::new(stream::Repeat::new("a9b8c9\n", 50_000));
+    assert_eq!(99 * 50_000, stream_part1(reader).unwrap());
+
+    let reader = io::BufReader::new(stream::Repeat::new("xtwone3four\n", 50_000));
+    assert_eq!(24 * 50_000, stream_part2(reader).unwrap());
+}
+
+#[test]
+fn test_scanner_overlaps() {
+    let scanner = DigitScanner::english();
+    let values: Vec<_> = scanner.matches("xtwone3").map(|m| m.value).collect();
+
+    assert_eq!(vec![2, 1, 3], values);
+    assert_eq!(Some(21), scanner.calibration_value("twone"));
+    assert_eq!(Some(83), scanner.calibration_value("eighthree"));
+    assert_eq!(None, scanner.calibration_value("abc"));
+
+    let german = DigitScanner::new([("eins", 1), ("zwei", 2), ("drei", 3), ("sieben", 7)]);
+    assert_eq!(Some(17), german.calibration_value("xeinsiebenx"));
+    assert_eq!(Some(32), german.calibration_value("dreinszwei"));
+}
+
+#[test]
+fn test_scanner_matches_prefixes() {
+    // Random lines built from fragments of the digit words, so that partial and overlapping
+    // words are common
+    const FRAGMENTS: [&str; 12] = [
+        "one", "tw", "o", "thr", "ee", "ight", "nin", "e", "s", "even", "x", "5",
+    ];
+    let scanner = DigitScanner::english();
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+
+    for _ in 0..2_000 {
+        let mut line = String::new();
+        for _ in 0..12 {
+            // xorshift64
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            line.push_str(FRAGMENTS[state as usize % FRAGMENTS.len()]);
+        }
+
+        let matches: Vec<_> = scanner.matches(&line).collect();
+        if matches.is_empty() {
+            continue;
+        }
+
+        let first = matches.iter().min_by_key(|m| m.start).unwrap();
+        let last = matches.iter().max_by_key(|m| m.end).unwrap();
+
+        assert_eq!(match_first_digit(&line), first.value, "{}", line);
+        assert_eq!(match_last_digit(&line), last.value, "{}", line);
+        assert_eq!(
+            spelled_calibration_value(&line),
+            scanner.calibration_value(&line).unwrap()
+        );
+    }
+}
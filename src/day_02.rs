@@ -1,45 +1,157 @@
+use crate::stream;
 use aoc_runner_derive::aoc;
 use nom::{
-    branch::alt,
     bytes::complete::tag,
-    character::complete::{u16, u8},
+    character::complete::{alpha1, u16, u32},
     combinator::{all_consuming, map},
     multi::separated_list1,
-    sequence::{delimited, pair},
+    sequence::{delimited, pair, separated_pair},
     IResult,
 };
-use std::ops::Add;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+const PART_2_COLOURS: [&str; 3] = ["red", "green", "blue"];
 
 #[aoc(day2, part1)]
 fn solve_part1(input: &str) -> u16 {
+    possible_games(input, &Bag::part_1()).into_iter().sum()
+}
+
+#[aoc(day2, part2)]
+fn solve_part2(input: &str) -> usize {
+    minimum_bags(input)
+        .into_iter()
+        .map(|(_, bag)| bag.power(&PART_2_COLOURS))
+        .sum()
+}
+
+/// Summed as `usize`, since a long enough stream would overflow the `u16` ids
+pub fn stream_part1<R: BufRead>(reader: R) -> io::Result<usize> {
+    let bag = Bag::part_1();
+
+    stream::fold_lines(reader, 0, |total, line| {
+        let game = parse_game(line);
+
+        if game.possible_with(&bag) {
+            total + game.id as usize
+        } else {
+            total
+        }
+    })
+}
+
+pub fn stream_part2<R: BufRead>(reader: R) -> io::Result<usize> {
+    stream::fold_lines(reader, 0, |total, line| {
+        total + parse_game(line).minimum_bag().power(&PART_2_COLOURS)
+    })
+}
+
+/// The ids of the games that could have been played with the given bag
+pub fn possible_games(input: &str, bag: &Bag) -> Vec<u16> {
     input
         .lines()
-        .filter_map(|line| {
-            let game = parse_game(line);
+        .map(parse_game)
+        .filter(|game| game.possible_with(bag))
+        .map(|game| game.id)
+        .collect()
+}
 
-            game.possible_part_1().then_some(game.id)
+/// The smallest bag that could have been used for each game, by game id
+pub fn minimum_bags(input: &str) -> Vec<(u16, Bag)> {
+    input
+        .lines()
+        .map(|line| {
+            let game = parse_game(line);
+            (game.id, game.minimum_bag())
         })
-        .sum()
+        .collect()
+}
+
+pub fn smallest_bag(input: &str) -> Bag {
+    minimum_bags(input)
+        .into_iter()
+        .fold(Bag::new(), |bag, (_, minimum)| bag.max(&minimum))
+}
+
+/// Cubes of each colour, for a bag or a single pull. Unmentioned colours have none.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bag {
+    counts: BTreeMap<String, u32>,
+}
+
+impl Bag {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn part_1() -> Self {
+        Self::new()
+            .with("red", 12)
+            .with("green", 13)
+            .with("blue", 14)
+    }
+
+    pub fn with(mut self, colour: &str, count: u32) -> Self {
+        *self.counts.entry(colour.to_string()).or_default() += count;
+        self
+    }
+
+    pub fn count(&self, colour: &str) -> u32 {
+        self.counts.get(colour).copied().unwrap_or(0)
+    }
+
+    pub fn contains(&self, other: &Bag) -> bool {
+        other
+            .counts
+            .iter()
+            .all(|(colour, count)| self.count(colour) >= *count)
+    }
+
+    /// The bag holding the larger count of each colour from either bag
+    pub fn max(mut self, other: &Bag) -> Self {
+        for (colour, count) in &other.counts {
+            let current = self.counts.entry(colour.clone()).or_default();
+            *current = (*current).max(*count);
+        }
+
+        self
+    }
+
+    /// A colour the bag doesn't hold makes the power 0
+    pub fn power(&self, colours: &[&str]) -> usize {
+        colours
+            .iter()
+            .map(|colour| self.count(colour) as usize)
+            .product()
+    }
+}
+
+impl fmt::Display for Bag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(colour, count)| format!("{} {}", count, colour))
+            .collect();
+
+        write!(f, "{}", counts.join(", "))
+    }
 }
 
 struct Game {
     id: u16,
-    pulls: Vec<Pull>,
+    pulls: Vec<Bag>,
 }
 
 impl Game {
-    fn possible_part_1(&self) -> bool {
-        const MAX_RED: u8 = 12;
-        const MAX_GREEN: u8 = 13;
-        const MAX_BLUE: u8 = 14;
-
-        let max = self.pulls.iter().copied().fold(Pull::default(), Pull::max);
-
-        max.red <= MAX_RED && max.green <= MAX_GREEN && max.blue <= MAX_BLUE
+    fn possible_with(&self, bag: &Bag) -> bool {
+        self.pulls.iter().all(|pull| bag.contains(pull))
     }
 
-    fn minimum_possible_pull(self) -> Pull {
-        self.pulls.into_iter().fold(Pull::default(), Pull::max)
+    fn minimum_bag(&self) -> Bag {
+        self.pulls.iter().fold(Bag::new(), Bag::max)
     }
 }
 
@@ -56,93 +168,72 @@ fn parse_game_id(input: &str) -> IResult<&str, u16> {
     delimited(tag("Game "), u16, tag(": "))(input)
 }
 
-fn parse_pull_list(input: &str) -> IResult<&str, Vec<Pull>> {
+fn parse_pull_list(input: &str) -> IResult<&str, Vec<Bag>> {
     separated_list1(tag("; "), parse_pull)(input)
 }
 
-fn parse_pull(input: &str) -> IResult<&str, Pull> {
-    map(
-        separated_list1(tag(", "), alt((parse_red, parse_green, parse_blue))),
-        |list| list.into_iter().fold(Pull::default(), Pull::add),
-    )(input)
+fn parse_pull(input: &str) -> IResult<&str, Bag> {
+    map(separated_list1(tag(", "), parse_cubes), |list| {
+        list.into_iter()
+            .fold(Bag::new(), |bag, (count, colour)| bag.with(colour, count))
+    })(input)
 }
 
-fn parse_red(input: &str) -> IResult<&str, Pull> {
-    map(pair(u8, tag(" red")), |(count, _)| Pull::new_red(count))(input)
+fn parse_cubes(input: &str) -> IResult<&str, (u32, &str)> {
+    separated_pair(u32, tag(" "), alpha1)(input)
 }
 
-fn parse_green(input: &str) -> IResult<&str, Pull> {
-    map(pair(u8, tag(" green")), |(count, _)| Pull::new_green(count))(input)
-}
+#[cfg(test)]
+const EXAMPLE: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green";
 
-fn parse_blue(input: &str) -> IResult<&str, Pull> {
-    map(pair(u8, tag(" blue")), |(count, _)| Pull::new_blue(count))(input)
+#[test]
+fn test_part1() {
+    assert_eq!(8, solve_part1(EXAMPLE));
 }
 
-#[derive(Clone, Copy, Default)]
-struct Pull {
-    red: u8,
-    green: u8,
-    blue: u8,
+#[test]
+fn test_part2() {
+    assert_eq!(2286, solve_part2(EXAMPLE));
 }
 
-impl Pull {
-    fn new_red(count: u8) -> Self {
-        Self {
-            red: count,
-            green: 0,
-            blue: 0,
-        }
-    }
-
-    fn new_green(count: u8) -> Self {
-        Self {
-            red: 0,
-            green: count,
-            blue: 0,
-        }
-    }
-
-    fn new_blue(count: u8) -> Self {
-        Self {
-            red: 0,
-            green: 0,
-            blue: count,
-        }
-    }
-
-    fn max(self, other: Self) -> Self {
-        Self {
-            red: self.red.max(other.red),
-            green: self.green.max(other.green),
-            blue: self.blue.max(other.blue),
-        }
-    }
-
-    fn power(self) -> usize {
-        self.red as usize * self.green as usize * self.blue as usize
-    }
+#[test]
+fn test_bag_queries() {
+    let minimum = minimum_bags(EXAMPLE);
+    assert_eq!("6 blue, 2 green, 4 red", minimum[0].1.to_string());
+    assert_eq!(
+        Bag::new().with("blue", 6).with("green", 2).with("red", 4),
+        minimum[0].1
+    );
+
+    let smallest = smallest_bag(EXAMPLE);
+    assert_eq!("15 blue, 13 green, 20 red", smallest.to_string());
+    assert_eq!(vec![1, 2, 3, 4, 5], possible_games(EXAMPLE, &smallest));
+    assert_eq!(vec![1, 2, 5], possible_games(EXAMPLE, &Bag::part_1()));
+
+    let rainbow = "Game 7: 2 violet, 1 red; 3 violet\nGame 8: 1 red";
+    assert_eq!(3, smallest_bag(rainbow).count("violet"));
+    assert_eq!(vec![8], possible_games(rainbow, &Bag::part_1()));
 }
 
-impl Add for Pull {
-    type Output = Self;
+#[test]
+fn test_streaming() {
+    assert_eq!(8, stream_part1(EXAMPLE.as_bytes()).unwrap());
+    assert_eq!(2286, stream_part2(EXAMPLE.as_bytes()).unwrap());
 
-    fn add(self, rhs: Self) -> Self {
-        Pull {
-            red: self.red + rhs.red,
-            green: self.green + rhs.green,
-            blue: self.blue + rhs.blue,
-        }
-    }
+    let game = "Game 60000: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green\n";
+    let reader = io::BufReader::new(stream::Repeat::new(game, 10_000));
+    assert_eq!(600_000_000, stream_part1(reader).unwrap());
 }
 
-#[aoc(day2, part2)]
-fn solve_part2(input: &str) -> usize {
-    input
-        .lines()
-        .map(|line| {
-            let game = parse_game(line);
-            game.minimum_possible_pull().power()
-        })
-        .sum()
+#[test]
+fn test_missing_colour_power() {
+    let input = "Game 1: 3 red, 2 green; 1 red\nGame 2: 1 red, 1 green, 2 blue";
+
+    assert_eq!(2, solve_part2(input));
+    assert_eq!(2, stream_part2(input.as_bytes()).unwrap());
+    assert_eq!(0, Bag::new().power(&PART_2_COLOURS));
 }
@@ -3,53 +3,210 @@ use std::collections::HashMap;
 
 #[aoc(day3, part1)]
 fn solve_part1(input: &str) -> usize {
-    let mut schematic = Schematic::from(input);
-    let mut sum = 0;
-
-    // Row & Column count determined manually by inspecting input
-    for row in 0..140 {
-        for col in 0..140 {
-            let point = Point::new(row as isize, col as isize);
-            if schematic.is_symbol(point) {
-                for neighbor in point.neighbors() {
-                    if schematic.is_digit(neighbor) {
-                        let (number, already_seen) = schematic.read_part_number(neighbor);
-                        if !already_seen {
-                            sum += number;
-                        }
-                    }
-                }
-            }
-        }
-    }
-
-    sum
+    let index = PartIndex::new(input);
+    index.part_numbers().map(|number| number.value).sum()
 }
 
 #[aoc(day3, part2)]
 fn solve_part2(input: &str) -> usize {
-    let mut schematic = Schematic::from(input);
+    let index = PartIndex::new(input);
+
+    index
+        .gears(2)
+        .map(|(_, numbers)| numbers.iter().map(|number| number.value).product::<usize>())
+        .sum()
+}
 
-    let mut sum = 0;
+/// Every number as CSV, once per symbol it touches, or once with empty symbol columns
+pub fn part_report(input: &str) -> String {
+    let index = PartIndex::new(input);
+    let mut report =
+        String::from("symbol,symbol_row,symbol_col,number,number_row,start_col,end_col\n");
 
-    // Row & Column count determined manually by inspecting input
-    for row in 0..140 {
-        for col in 0..140 {
-            let point = Point::new(row as isize, col as isize);
-            sum += schematic.read_gear(point);
+    for (symbol, numbers) in index.symbols() {
+        for number in numbers {
+            report.push_str(&format!(
+                "{},{},{},{}\n",
+                symbol.chr,
+                symbol.row,
+                symbol.col,
+                number.to_csv()
+            ));
         }
     }
 
-    sum
+    for number in index.orphans() {
+        report.push_str(&format!(",,,{}\n", number.to_csv()));
+    }
+
+    report
+}
+
+/// A number and the columns it occupies, inclusive of both ends
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NumberSpan {
+    pub value: usize,
+    pub row: usize,
+    pub start_col: usize,
+    pub end_col: usize,
+}
+
+impl NumberSpan {
+    fn to_csv(self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.value, self.row, self.start_col, self.end_col
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub chr: char,
+    pub row: usize,
+    pub col: usize,
+}
+
+/// Every number in the schematic, indexed by the symbols next to it
+pub struct PartIndex {
+    numbers: Vec<NumberSpan>,
+    symbols: Vec<(Symbol, Vec<usize>)>,
+    symbol_counts: Vec<usize>,
+}
+
+impl PartIndex {
+    pub fn new(input: &str) -> Self {
+        Self::from(&Schematic::from(input))
+    }
+
+    pub fn numbers(&self) -> &[NumberSpan] {
+        &self.numbers
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = (Symbol, Vec<&NumberSpan>)> + '_ {
+        self.symbols
+            .iter()
+            .map(|(symbol, numbers)| (*symbol, self.resolve(numbers)))
+    }
+
+    pub fn part_numbers(&self) -> impl Iterator<Item = &NumberSpan> {
+        self.numbers
+            .iter()
+            .zip(&self.symbol_counts)
+            .filter(|(_, count)| **count > 0)
+            .map(|(number, _)| number)
+    }
+
+    /// The numbers adjacent to no symbol at all
+    pub fn orphans(&self) -> impl Iterator<Item = &NumberSpan> {
+        self.numbers
+            .iter()
+            .zip(&self.symbol_counts)
+            .filter(|(_, count)| **count == 0)
+            .map(|(number, _)| number)
+    }
+
+    /// The numbers adjacent to any occurrence of the given symbol, each listed once
+    pub fn adjacent_to(&self, chr: char) -> Vec<&NumberSpan> {
+        let mut indices: Vec<usize> = self
+            .symbols
+            .iter()
+            .filter(|(symbol, _)| symbol.chr == chr)
+            .flat_map(|(_, numbers)| numbers.iter().copied())
+            .collect();
+        indices.sort_unstable();
+        indices.dedup();
+
+        self.resolve(&indices)
+    }
+
+    /// The `*` symbols adjacent to exactly `parts` numbers
+    pub fn gears(&self, parts: usize) -> impl Iterator<Item = (Symbol, Vec<&NumberSpan>)> + '_ {
+        self.symbols()
+            .filter(move |(symbol, numbers)| symbol.chr == '*' && numbers.len() == parts)
+    }
+
+    fn resolve(&self, indices: &[usize]) -> Vec<&NumberSpan> {
+        indices.iter().map(|index| &self.numbers[*index]).collect()
+    }
+}
+
+impl From<&Schematic> for PartIndex {
+    fn from(schematic: &Schematic) -> Self {
+        let mut numbers = Vec::new();
+        let mut owners = HashMap::new();
+
+        for row in 0..schematic.rows {
+            let mut col = 0;
+
+            while col < schematic.cols {
+                let start = Point::new(row as isize, col as isize);
+                if !schematic.is_digit(start) {
+                    col += 1;
+                    continue;
+                }
+
+                let mut value = 0;
+                let mut current = start;
+                while let Some(digit) = schematic.digit(current) {
+                    value = value * 10 + digit;
+                    owners.insert(current, numbers.len());
+                    current = current.right();
+                }
+
+                let end_col = current.col as usize - 1;
+                numbers.push(NumberSpan {
+                    value,
+                    row,
+                    start_col: col,
+                    end_col,
+                });
+                col = end_col + 1;
+            }
+        }
+
+        let mut symbol_counts = vec![0; numbers.len()];
+        let symbols = schematic
+            .symbols()
+            .map(|point| {
+                let mut adjacent: Vec<usize> = point
+                    .neighbors()
+                    .filter_map(|neighbor| owners.get(&neighbor).copied())
+                    .collect();
+                adjacent.sort_unstable();
+                adjacent.dedup();
+
+                for index in &adjacent {
+                    symbol_counts[*index] += 1;
+                }
+
+                let symbol = Symbol {
+                    chr: schematic.values[&point],
+                    row: point.row as usize,
+                    col: point.col as usize,
+                };
+                (symbol, adjacent)
+            })
+            .collect();
+
+        Self {
+            numbers,
+            symbols,
+            symbol_counts,
+        }
+    }
 }
 
 struct Schematic {
+    rows: usize,
+    cols: usize,
     values: HashMap<Point, char>,
-    seen_part_numbers: HashMap<Point, usize>,
 }
 
 impl From<&str> for Schematic {
     fn from(input: &str) -> Self {
+        let rows = input.lines().count();
+        let cols = input.lines().map(str::len).max().unwrap_or(0);
         let values = input
             .lines()
             .enumerate()
@@ -59,14 +216,19 @@ impl From<&str> for Schematic {
                     .map(move |(col, chr)| (Point::new(row as isize, col as isize), chr))
             })
             .collect();
-        Self {
-            values,
-            seen_part_numbers: HashMap::new(),
-        }
+        Self { rows, cols, values }
     }
 }
 
 impl Schematic {
+    fn symbols(&self) -> impl Iterator<Item = Point> + '_ {
+        (0..self.rows)
+            .flat_map(move |row| {
+                (0..self.cols).map(move |col| Point::new(row as isize, col as isize))
+            })
+            .filter(|point| self.is_symbol(*point))
+    }
+
     fn is_symbol(&self, point: Point) -> bool {
         match self.values.get(&point) {
             Some(chr) => !chr.is_ascii_digit() && chr != &'.',
@@ -75,71 +237,18 @@ impl Schematic {
     }
 
     fn is_digit(&self, point: Point) -> bool {
-        match self.values.get(&point) {
-            Some(chr) => chr.is_ascii_digit(),
-            None => false,
-        }
+        self.digit(point).is_some()
     }
 
-    fn read_gear(&mut self, point: Point) -> usize {
-        if self.values.get(&point) == Some(&'*') {
-            let mut first = 0;
-            let mut second = 0;
-
-            for neighbor in point.neighbors() {
-                if self.is_digit(neighbor) {
-                    let (candidate, _) = self.read_part_number(neighbor);
-
-                    if first == 0 {
-                        first = candidate;
-                    } else if second == 0 {
-                        if first != candidate {
-                            second = candidate;
-                        }
-                    } else if first != candidate && second != candidate {
-                        return 0;
-                    }
-                }
-            }
-
-            first * second
-        } else {
-            0
-        }
-    }
-
-    fn read_part_number(&mut self, start: Point) -> (usize, bool) {
-        let mut current = start;
-        while current.col > 0 {
-            let test = current.left();
-            if self.values.get(&test).unwrap().is_ascii_digit() {
-                current = test;
-            } else {
-                break;
-            }
-        }
-
-        let initial = current;
-        let mut number = 0;
-        while let Some(chr) = self.values.get(&current) {
-            match chr.to_digit(10) {
-                Some(digit) => {
-                    number *= 10;
-                    number += digit as usize;
-                    current = current.right();
-                }
-                None => break,
-            }
-        }
-
-        (
-            number,
-            self.seen_part_numbers.insert(initial, number).is_some(),
-        )
+    fn digit(&self, point: Point) -> Option<usize> {
+        self.values
+            .get(&point)
+            .and_then(|chr| chr.to_digit(10))
+            .map(|digit| digit as usize)
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 struct Point {
     row: isize,
     col: isize,
@@ -164,13 +273,6 @@ impl Point {
         .into_iter()
     }
 
-    fn left(self) -> Self {
-        Self {
-            row: self.row,
-            col: self.col - 1,
-        }
-    }
-
     fn right(self) -> Self {
         Self {
             row: self.row,
@@ -178,3 +280,71 @@ impl Point {
         }
     }
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598..";
+
+#[test]
+fn test_part1() {
+    assert_eq!(4361, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(467835, solve_part2(EXAMPLE));
+}
+
+#[test]
+fn test_non_square() {
+    let schematic = Schematic::from("12.\n.*.\n..3\n4..\n.#5");
+    assert_eq!((5, 3), (schematic.rows, schematic.cols));
+    assert_eq!(
+        vec![Point::new(1, 1), Point::new(4, 1)],
+        schematic.symbols().collect::<Vec<_>>()
+    );
+
+    assert_eq!(12 + 3 + 4 + 5, solve_part1("12.\n.*.\n..3\n4..\n.#5"));
+    assert_eq!(12 * 3, solve_part2("12.\n.*.\n..3\n4..\n.#5"));
+}
+
+#[test]
+fn test_index_queries() {
+    let index = PartIndex::new(EXAMPLE);
+    let values = |numbers: Vec<&NumberSpan>| numbers.iter().map(|n| n.value).collect::<Vec<_>>();
+
+    assert_eq!(10, index.numbers().len());
+    assert_eq!(vec![114, 58], values(index.orphans().collect()));
+    assert_eq!(vec![467, 35, 617, 755, 598], values(index.adjacent_to('*')));
+    assert_eq!(
+        vec![617],
+        values(index.gears(1).flat_map(|(_, n)| n).collect())
+    );
+    assert_eq!(2, index.gears(2).count());
+
+    // Querying again gives the same answer, as nothing is consumed
+    assert_eq!(4361, index.part_numbers().map(|n| n.value).sum::<usize>());
+    assert_eq!(4361, index.part_numbers().map(|n| n.value).sum::<usize>());
+}
+
+#[test]
+fn test_part_report() {
+    let report = part_report("1.2\n.*.\n4.#\n..5");
+    let expected = "symbol,symbol_row,symbol_col,number,number_row,start_col,end_col
+*,1,1,1,0,0,0
+*,1,1,2,0,2,2
+*,1,1,4,2,0,0
+#,2,2,5,3,2,2
+";
+
+    assert_eq!(expected, report);
+    assert_eq!(Some(",,,77,1,0,1"), part_report("...\n77.").lines().nth(1));
+}
@@ -1,52 +1,204 @@
+use crate::stream;
 use aoc_runner_derive::aoc;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashSet, VecDeque};
+use std::io::{self, BufRead};
 
 #[aoc(day4, part1)]
 fn solve_part1(input: &str) -> usize {
     input
         .lines()
-        .map(|card| {
-            let matches = match_count(card);
-            if matches > 0 {
-                2_usize.pow(matches as u32 - 1)
-            } else {
-                0
-            }
-        })
+        .map(|line| Scratchcard::from(line).points())
         .sum()
 }
 
 #[aoc(day4, part2)]
 fn solve_part2(input: &str) -> usize {
-    let mut cards = HashMap::new();
+    let cards: Vec<_> = input.lines().map(Scratchcard::from).collect();
+    Cascade::new(&cards).total()
+}
+
+/// Describes how every copy of each card was won, one card per line
+pub fn explain_cascade(input: &str) -> String {
+    let cards: Vec<_> = input.lines().map(Scratchcard::from).collect();
+    let cascade = Cascade::new(&cards);
+
+    (0..cards.len())
+        .map(|index| {
+            let mut parts = vec!["1 original".to_string()];
+            parts.extend(
+                cascade
+                    .sources(index)
+                    .into_iter()
+                    .map(|(id, copies)| format!("{} from card {}", copies, id)),
+            );
+
+            format!(
+                "Card {}: {} = {}",
+                cascade.ids[index],
+                cascade.copies[index],
+                parts.join(" + ")
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn stream_part1<R: BufRead>(reader: R) -> io::Result<usize> {
+    stream::fold_lines(reader, 0, |total, card| {
+        total + Scratchcard::from(card).points()
+    })
+}
 
-    for (index, matches) in input.lines().map(match_count).enumerate() {
-        let id = index + 1;
-        let card_count_ref = cards.entry(id).or_default();
-        *card_count_ref += 1;
-        let card_count: usize = *card_count_ref;
+/// Only the copies won for the cards still to come are kept
+pub fn stream_part2<R: BufRead>(reader: R) -> io::Result<usize> {
+    let mut pending = VecDeque::new();
 
-        for step in 1..=matches {
-            *cards.entry(id + step).or_default() += card_count;
+    stream::fold_lines(reader, 0, |total, card| {
+        let copies = 1 + pending.pop_front().unwrap_or(0);
+        let matches = Scratchcard::from(card).matches();
+
+        if pending.len() < matches {
+            pending.resize(matches, 0);
+        }
+        for won in pending.iter_mut().take(matches) {
+            *won += copies;
         }
+
+        total + copies
+    })
+}
+
+pub struct Scratchcard {
+    pub id: usize,
+    pub winning: Vec<usize>,
+    pub held: Vec<usize>,
+}
+
+impl Scratchcard {
+    pub fn matches(&self) -> usize {
+        let winning: HashSet<_> = self.winning.iter().collect();
+        self.held
+            .iter()
+            .filter(|number| winning.contains(number))
+            .count()
     }
 
-    cards.values().copied().sum()
+    pub fn points(&self) -> usize {
+        let matches = self.matches();
+        if matches > 0 {
+            2_usize.pow(matches as u32 - 1)
+        } else {
+            0
+        }
+    }
 }
 
-fn match_count(card: &str) -> usize {
-    let (_, card_str) = card.split_once(": ").unwrap();
-    let (winning_str, have_str) = card_str.split_once(" | ").unwrap();
-    let winning: HashSet<_> = winning_str
-        .split_whitespace()
-        .map(|num| num.parse::<usize>().unwrap())
-        .collect();
+impl From<&str> for Scratchcard {
+    fn from(card: &str) -> Self {
+        let (id_str, card_str) = card.split_once(": ").unwrap();
+        let (winning_str, held_str) = card_str.split_once(" | ").unwrap();
+        let numbers = |numbers: &str| {
+            numbers
+                .split_whitespace()
+                .map(|num| num.parse::<usize>().unwrap())
+                .collect()
+        };
 
-    have_str
-        .split_whitespace()
-        .filter(|num| {
-            let value = num.parse::<usize>().unwrap();
-            winning.contains(&value)
-        })
-        .count()
+        Self {
+            id: id_str.trim_start_matches("Card").trim().parse().unwrap(),
+            winning: numbers(winning_str),
+            held: numbers(held_str),
+        }
+    }
+}
+
+/// The copies of each card once every card has been scratched
+pub struct Cascade {
+    ids: Vec<usize>,
+    matches: Vec<usize>,
+    copies: Vec<usize>,
+    max_matches: usize,
+}
+
+impl Cascade {
+    pub fn new(cards: &[Scratchcard]) -> Self {
+        let matches: Vec<_> = cards.iter().map(Scratchcard::matches).collect();
+
+        // Copies won are recorded at either end of the run of cards they apply to
+        let mut changes = vec![0_isize; cards.len() + 1];
+        let mut copies = Vec::with_capacity(cards.len());
+        let mut won = 0;
+
+        for (index, count) in matches.iter().enumerate() {
+            won += changes[index];
+            let current = won + 1;
+            copies.push(current as usize);
+
+            let end = (index + count + 1).min(cards.len());
+            changes[index + 1] += current;
+            changes[end] -= current;
+        }
+
+        Self {
+            ids: cards.iter().map(|card| card.id).collect(),
+            max_matches: matches.iter().copied().max().unwrap_or(0),
+            matches,
+            copies,
+        }
+    }
+
+    pub fn copies(&self) -> &[usize] {
+        &self.copies
+    }
+
+    pub fn total(&self) -> usize {
+        self.copies.iter().sum()
+    }
+
+    /// The cards that won copies of the card at `index`, as (id, copies won)
+    pub fn sources(&self, index: usize) -> Vec<(usize, usize)> {
+        (index.saturating_sub(self.max_matches)..index)
+            .filter(|source| source + self.matches[*source] >= index)
+            .map(|source| (self.ids[source], self.copies[source]))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
+
+#[test]
+fn test_streaming() {
+    assert_eq!(13, solve_part1(EXAMPLE));
+    assert_eq!(13, stream_part1(EXAMPLE.as_bytes()).unwrap());
+    assert_eq!(30, solve_part2(EXAMPLE));
+    assert_eq!(30, stream_part2(EXAMPLE.as_bytes()).unwrap());
+
+    // The last two cards of the example win nothing, so no copies spill over into the next
+    // repetition and each one adds the same 30 cards
+    let repeated = format!("{}\n", EXAMPLE).repeat(6);
+    assert_eq!(180, solve_part2(&repeated));
+    assert_eq!(180, stream_part2(repeated.as_bytes()).unwrap());
+}
+
+#[test]
+fn test_cascade() {
+    let cards: Vec<_> = EXAMPLE.lines().map(Scratchcard::from).collect();
+    let cascade = Cascade::new(&cards);
+
+    assert_eq!(&[1, 2, 4, 8, 14, 1], cascade.copies());
+    assert_eq!(vec![(1, 1), (3, 4), (4, 8)], cascade.sources(4));
+
+    let explanation = explain_cascade(EXAMPLE);
+    let mut lines = explanation.lines();
+    assert_eq!(Some("Card 1: 1 = 1 original"), lines.next());
+    assert_eq!(
+        Some("Card 4: 8 = 1 original + 1 from card 1 + 2 from card 2 + 4 from card 3"),
+        lines.nth(2)
+    );
 }
@@ -1,3 +1,4 @@
+use crate::interval::{Interval, IntervalSet};
 use aoc_runner_derive::aoc;
 
 #[aoc(day5, part1)]
@@ -17,28 +18,19 @@ fn solve_part2(input: &str) -> usize {
     let (seeds_line, maps) = input.split_once("\n\n").unwrap();
     let almanac = Almanac::from(maps);
 
-    let mut min_location = usize::MAX;
+    let mut seeds = IntervalSet::new();
     let mut seed_values = seeds_line[7..].split_ascii_whitespace();
 
-    loop {
-        let Some(start_str) = seed_values.next() else {
-            break;
-        };
+    while let Some(start_str) = seed_values.next() {
         let start = start_str.parse().unwrap();
         let length = seed_values.next().unwrap().parse().unwrap();
 
-        let possible_min = almanac.min_location_for_range(Range { start, length });
-
-        min_location = min_location.min(possible_min);
+        if let Some(interval) = Interval::with_length(start, length) {
+            seeds.insert(interval);
+        }
     }
 
-    min_location
-}
-
-#[derive(Debug)]
-struct Range {
-    start: usize,
-    length: usize,
+    almanac.seed_to_location().min_image(&seeds).unwrap()
 }
 
 struct Almanac {
@@ -56,19 +48,10 @@ impl Almanac {
         current
     }
 
-    fn min_location_for_range(&self, seed_range: Range) -> usize {
-        let location_ranges = self.maps.iter().fold(vec![seed_range], |ranges, map| {
-            ranges
-                .into_iter()
-                .flat_map(|range| map.lookup_range(range))
-                .collect()
-        });
-
-        location_ranges
-            .into_iter()
-            .map(|range| range.start)
-            .min()
-            .unwrap()
+    fn seed_to_location(&self) -> Map {
+        self.maps
+            .iter()
+            .fold(Map::identity(), |composed, map| composed.then(map))
     }
 }
 
@@ -80,132 +63,91 @@ impl From<&str> for Almanac {
     }
 }
 
+/// Sorted, disjoint source intervals covering every value, each with an offset to add
 struct Map {
-    ranges: Vec<RangeMap>,
+    pieces: Vec<(Interval, i128)>,
 }
 
 impl Map {
-    fn lookup_single(&self, source_value: usize) -> usize {
-        match self
-            .ranges
-            .iter()
-            .filter_map(|range| range.lookup(source_value))
-            .next()
-        {
-            Some(destination_value) => destination_value,
-            None => source_value,
+    fn identity() -> Self {
+        Self {
+            pieces: vec![(Interval::new(0, usize::MAX), 0)],
         }
     }
 
-    fn lookup_range(&self, range: Range) -> Vec<Range> {
-        let mut results = Vec::new();
-
-        let mut current = range.start;
-        let mut remaining = range.length;
-        let mut map_index = 0;
-
-        while remaining > 0 {
-            let Some(range_map) = self.ranges.get(map_index) else {
-                break;
-            };
-            map_index += 1;
+    fn lookup_single(&self, source_value: usize) -> usize {
+        let index = self
+            .pieces
+            .partition_point(|(interval, _)| interval.end < source_value);
+        let (_, offset) = self.pieces[index];
 
-            if range_map.source_start > current + remaining - 1 {
-                break;
-            }
+        apply(source_value, offset)
+    }
 
-            if range_map.source_start + range_map.length - 1 < current {
-                continue;
-            }
+    /// The map that applies this map and then `next`
+    fn then(&self, next: &Map) -> Map {
+        let mut pieces = Vec::new();
 
-            if current < range_map.source_start {
-                let length = range_map.source_start - current;
-                results.push(Range {
-                    start: current,
-                    length,
-                });
-                current = range_map.source_start;
-                remaining -= length;
-            }
+        for (interval, offset) in &self.pieces {
+            let image = Interval::new(apply(interval.start, *offset), apply(interval.end, *offset));
 
-            let diff = current - range_map.source_start;
-            let mapped_length = range_map.length - diff;
-
-            if mapped_length > remaining {
-                results.push(Range {
-                    start: range_map.destination_start + diff,
-                    length: remaining,
-                });
-                remaining = 0;
-                break;
-            } else {
-                results.push(Range {
-                    start: range_map.destination_start + diff,
-                    length: mapped_length,
-                });
-                current += mapped_length;
-                remaining -= mapped_length;
+            for (next_interval, next_offset) in &next.pieces {
+                if let Some(overlap) = image.intersection(next_interval) {
+                    let source =
+                        Interval::new(apply(overlap.start, -offset), apply(overlap.end, -offset));
+                    pieces.push((source, offset + next_offset));
+                }
             }
         }
 
-        if remaining > 0 {
-            results.push(Range {
-                start: current,
-                length: remaining,
-            });
-        }
-
-        results
+        pieces.sort_by_key(|(interval, _)| interval.start);
+        Self { pieces }
     }
-}
 
-impl From<&str> for Map {
-    fn from(value: &str) -> Self {
-        let mut ranges: Vec<_> = value.lines().skip(1).map(RangeMap::from).collect();
-        ranges.sort_by_key(|r| r.source_start);
-
-        Self { ranges }
+    /// Each piece only shifts its values, so its smallest source gives its smallest result
+    fn min_image(&self, sources: &IntervalSet) -> Option<usize> {
+        self.pieces
+            .iter()
+            .filter_map(|(interval, offset)| {
+                let overlap = sources.intersection(&IntervalSet::from_iter([*interval]));
+                overlap.min().map(|min| apply(min, *offset))
+            })
+            .min()
     }
 }
 
-struct RangeMap {
-    source_start: usize,
-    destination_start: usize,
-    length: usize,
-}
-
-impl RangeMap {
-    fn lookup(&self, source_value: usize) -> Option<usize> {
-        let Some(diff) = source_value.checked_sub(self.source_start) else {
-            return None;
-        };
-
-        if diff > self.length - 1 {
-            return None;
-        }
-
-        Some(self.destination_start + diff)
-    }
+fn apply(value: usize, offset: i128) -> usize {
+    (value as i128 + offset) as usize
 }
 
-impl From<&str> for RangeMap {
+impl From<&str> for Map {
     fn from(value: &str) -> Self {
-        let mut values = value.split_ascii_whitespace();
-        let destination_start = values.next().unwrap().parse().unwrap();
-        let source_start = values.next().unwrap().parse().unwrap();
-        let length = values.next().unwrap().parse().unwrap();
-
-        Self {
-            destination_start,
-            source_start,
-            length,
-        }
+        let mut pieces: Vec<_> = value
+            .lines()
+            .skip(1)
+            .filter_map(|line| {
+                let mut values = line.split_ascii_whitespace();
+                let destination_start: usize = values.next().unwrap().parse().unwrap();
+                let source_start = values.next().unwrap().parse().unwrap();
+                let length = values.next().unwrap().parse().unwrap();
+
+                let interval = Interval::with_length(source_start, length)?;
+                Some((interval, destination_start as i128 - source_start as i128))
+            })
+            .collect();
+
+        // Fill the gaps between the listed ranges with pieces that leave the value unchanged
+        let listed: IntervalSet = pieces.iter().map(|(interval, _)| *interval).collect();
+        let everything = IntervalSet::from_iter([Interval::new(0, usize::MAX)]);
+        pieces.extend(everything.difference(&listed).iter().map(|gap| (*gap, 0)));
+        pieces.sort_by_key(|(interval, _)| interval.start);
+
+        Self { pieces }
     }
 }
 
-#[test]
-fn test_part_2() {
-    let data = "seeds: 79 14 55 13
+#[cfg(test)]
+const EXAMPLE: &str = "seeds: 79 14 55 13
 
 seed-to-soil map:
 50 98 2
@@ -239,5 +181,39 @@ humidity-to-location map:
 60 56 37
 56 93 4";
 
-    assert_eq!(solve_part2(data), 46);
+#[test]
+fn test_part_1() {
+    assert_eq!(solve_part1(EXAMPLE), 35);
+}
+
+#[test]
+fn test_part_2() {
+    assert_eq!(solve_part2(EXAMPLE), 46);
+}
+
+#[test]
+fn test_composed_map() {
+    let almanac = Almanac::from(EXAMPLE.split_once("\n\n").unwrap().1);
+    let composed = almanac.seed_to_location();
+
+    for seed in 0..200 {
+        assert_eq!(
+            almanac.location_for_seed(seed),
+            composed.lookup_single(seed)
+        );
+    }
+}
+
+#[test]
+fn test_near_max() {
+    let top = usize::MAX - 9;
+    let map = Map::from(format!("a-to-b map:\n0 {} 10\n{} 0 10", top, top).as_str());
+
+    assert_eq!(0, map.lookup_single(top));
+    assert_eq!(9, map.lookup_single(usize::MAX));
+    assert_eq!(usize::MAX, map.lookup_single(9));
+    assert_eq!(10, map.lookup_single(10));
+
+    let seeds = IntervalSet::from_iter([Interval::new(usize::MAX - 2, usize::MAX)]);
+    assert_eq!(Some(7), map.then(&Map::identity()).min_image(&seeds));
 }
@@ -1,10 +1,21 @@
 use aoc_runner_derive::aoc;
-use std::cmp::Ordering;
+use std::cmp::{Ordering, Reverse};
 use std::collections::HashMap;
+use std::fmt;
 
 #[aoc(day7, part1)]
 fn solve_part1(input: &str) -> usize {
-    let mut hands: Vec<_> = input.lines().map(Hand::for_part_1).collect();
+    total_winnings(input, &RuleSet::STANDARD)
+}
+
+#[aoc(day7, part2)]
+fn solve_part2(input: &str) -> usize {
+    total_winnings(input, &RuleSet::JOKERS_WILD)
+}
+
+/// The total winnings for a list of hands and bids when played under the given rules
+pub fn total_winnings(input: &str, rules: &RuleSet) -> usize {
+    let mut hands: Vec<_> = input.lines().map(|line| Hand::new(line, rules)).collect();
     hands.sort();
 
     hands
@@ -14,59 +25,193 @@ fn solve_part1(input: &str) -> usize {
         .sum()
 }
 
-#[aoc(day7, part2)]
-fn solve_part2(input: &str) -> usize {
-    let mut hands: Vec<_> = input.lines().map(Hand::for_part_2).collect();
+/// Every hand from weakest to strongest with its rank and winnings, then the total
+pub fn ranked_hands(input: &str, rules: &RuleSet) -> String {
+    let mut hands: Vec<_> = input.lines().map(|line| Hand::new(line, rules)).collect();
     hands.sort();
 
-    hands
-        .into_iter()
+    let mut total = 0;
+    let mut lines: Vec<_> = hands
+        .iter()
         .enumerate()
-        .map(|(index, hand)| (index + 1) * hand.bid)
-        .sum()
+        .map(|(index, hand)| {
+            let winnings = (index + 1) * hand.bid;
+            total += winnings;
+
+            format!(
+                "{:>5} {} {:<8} {:>5} {:>8}",
+                index + 1,
+                hand.text,
+                format!("{:?}", hand.category),
+                hand.bid,
+                winnings
+            )
+        })
+        .collect();
+
+    lines.push(format!("Total: {}", total));
+    lines.join("\n")
 }
 
-struct Hand {
-    category: Category,
-    cards: Vec<Card>,
-    bid: usize,
+/// Explains how a hand (without a bid) is classified under the given rules
+pub fn explain_hand(cards: &str, rules: &RuleSet) -> Explanation {
+    let mut counts: Vec<(char, usize)> = Vec::new();
+    for card in cards.chars() {
+        match counts.iter_mut().find(|(label, _)| *label == card) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((card, 1)),
+        }
+    }
+    counts.sort_by_key(|(label, count)| (Reverse(*count), Reverse(rules.strength(*label))));
+
+    let target = rules.wild_target(cards);
+    let wilds = cards
+        .chars()
+        .enumerate()
+        .filter(|(_, card)| rules.is_wild(*card))
+        .map(|(position, _)| (position, target))
+        .collect();
+
+    Explanation {
+        cards: cards.to_string(),
+        category: Category::for_cards(cards, rules),
+        counts,
+        wilds,
+        tie_break: cards.chars().map(|card| rules.strength(card)).collect(),
+    }
 }
 
-impl Hand {
-    fn for_part_1(value: &str) -> Self {
-        let (cards_str, bid_str) = value.split_once(' ').unwrap();
-        let cards = cards_str.chars().map(Card::for_part_1).collect();
+#[derive(Debug)]
+pub struct Explanation {
+    pub cards: String,
+    pub category: Category,
+    /// Each card label and its count, most common first
+    pub counts: Vec<(char, usize)>,
+    /// The position of each wild card in the hand and the card it stood in for, if any
+    pub wilds: Vec<(usize, Option<char>)>,
+    pub tie_break: Vec<u8>,
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}: {:?}", self.cards, self.category)?;
+
+        let counts: Vec<_> = self
+            .counts
+            .iter()
+            .map(|(label, count)| format!("{}x{}", label, count))
+            .collect();
+        writeln!(f, "  counts: {}", counts.join(" "))?;
+
+        if !self.wilds.is_empty() {
+            let wilds: Vec<_> = self
+                .wilds
+                .iter()
+                .map(|(position, target)| match target {
+                    Some(label) => format!("#{} as {}", position + 1, label),
+                    None => format!("#{} unmatched", position + 1),
+                })
+                .collect();
+            writeln!(f, "  wild: {}", wilds.join(", "))?;
+        }
 
-        Self::new(cards, bid_str.parse().unwrap())
+        write!(f, "  tie-break: {:?}", self.tie_break)
     }
+}
 
-    fn for_part_2(value: &str) -> Self {
-        let (cards_str, bid_str) = value.split_once(' ').unwrap();
-        let cards = cards_str.chars().map(Card::for_part_2).collect();
+pub struct RuleSet {
+    /// Card labels from weakest to strongest
+    pub order: &'static str,
+    pub wild: &'static str,
+    pub upgrade: WildUpgrade,
+    pub hand_size: usize,
+}
+
+impl RuleSet {
+    pub const STANDARD: Self = Self {
+        order: "23456789TJQKA",
+        wild: "",
+        upgrade: WildUpgrade::JoinLargestGroup,
+        hand_size: 5,
+    };
+
+    pub const JOKERS_WILD: Self = Self {
+        order: "J23456789TQKA",
+        wild: "J",
+        upgrade: WildUpgrade::JoinLargestGroup,
+        hand_size: 5,
+    };
+
+    fn strength(&self, label: char) -> u8 {
+        match self.order.find(label) {
+            Some(strength) => strength as u8,
+            None => panic!("Unknown card '{}'", label),
+        }
+    }
 
-        Self::new(cards, bid_str.parse().unwrap())
+    fn is_wild(&self, label: char) -> bool {
+        self.wild.contains(label)
     }
 
-    fn new(cards: Vec<Card>, bid: usize) -> Self {
-        let category = Category::for_cards(&cards);
+    /// The most common other card, strongest on a tie, or the strongest card if all are wild
+    fn wild_target(&self, cards: &str) -> Option<char> {
+        match self.upgrade {
+            WildUpgrade::Unmatched => None,
+            WildUpgrade::JoinLargestGroup => cards
+                .chars()
+                .filter(|card| !self.is_wild(*card))
+                .max_by_key(|card| {
+                    let count = cards.chars().filter(|other| other == card).count();
+                    (count, self.strength(*card))
+                })
+                .or_else(|| self.order.chars().rev().find(|card| !self.is_wild(*card))),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub enum WildUpgrade {
+    /// Wild cards copy the most common other card
+    JoinLargestGroup,
+    /// Wild cards never match anything, not even each other
+    Unmatched,
+}
+
+struct Hand<'a> {
+    text: &'a str,
+    category: Category,
+    cards: Vec<u8>,
+    bid: usize,
+}
+
+impl<'a> Hand<'a> {
+    fn new(value: &'a str, rules: &RuleSet) -> Self {
+        let (cards_str, bid_str) = value.split_once(' ').unwrap();
+        assert_eq!(
+            rules.hand_size,
+            cards_str.chars().count(),
+            "Hand '{}' is the wrong size",
+            cards_str
+        );
 
         Self {
-            category,
-            cards,
-            bid,
+            text: cards_str,
+            category: Category::for_cards(cards_str, rules),
+            cards: cards_str.chars().map(|card| rules.strength(card)).collect(),
+            bid: bid_str.parse().unwrap(),
         }
     }
 }
 
-impl PartialEq for Hand {
+impl PartialEq for Hand<'_> {
     fn eq(&self, other: &Self) -> bool {
         self.category == other.category && self.cards == other.cards
     }
 }
 
-impl Eq for Hand {}
+impl Eq for Hand<'_> {}
 
-impl Ord for Hand {
+impl Ord for Hand<'_> {
     fn cmp(&self, other: &Self) -> Ordering {
         match self.category.cmp(&other.category) {
             Ordering::Less => Ordering::Less,
@@ -76,14 +221,14 @@ impl Ord for Hand {
     }
 }
 
-impl PartialOrd for Hand {
+impl PartialOrd for Hand<'_> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-enum Category {
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Category {
     HighCard,
     OnePair,
     TwoPair,
@@ -94,91 +239,177 @@ enum Category {
 }
 
 impl Category {
-    fn for_cards(cards: &[Card]) -> Category {
-        let mut card_map = HashMap::new();
-        for card in cards {
-            if card != &Card::Joker {
+    fn for_cards(cards: &str, rules: &RuleSet) -> Category {
+        let mut card_map: HashMap<char, usize> = HashMap::new();
+        let mut wilds = 0;
+
+        for card in cards.chars() {
+            if rules.is_wild(card) {
+                wilds += 1;
+            } else {
                 *card_map.entry(card).or_default() += 1;
             }
         }
 
         let mut counts: Vec<usize> = card_map.values().copied().collect();
-        counts.sort();
-
-        match counts.as_slice() {
-            // All non-joker cards are the same, so this is 5 of a kind
-            [5] | [4] | [3] | [2] | [1] | [] => Category::Quints,
-            // A single non-joker card different from the rest, so this is 4 of a kind
-            [1, 4] | [1, 3] | [1, 2] | [1, 1] => Category::Quads,
-            // A complete full house or two pairs with a joker
-            [2, 3] | [2, 2] => Category::Boat,
-            // Two non-joker cards different from the rest, so this is 3 of a kind
-            [1, 1, 3] | [1, 1, 2] | [1, 1, 1] => Category::Trips,
-            // Two pair can only exist without a joker, as a joker would make it into trips
-            [1, 2, 2] => Category::TwoPair,
-            // One pair or four different cards plus a joker
-            [1, 1, 1, 2] | [1, 1, 1, 1] => Category::OnePair,
-            // High card can only exist without a joker
-            [1, 1, 1, 1, 1] => Category::HighCard,
-            _ => unreachable!(),
+        counts.sort_by(|a, b| b.cmp(a));
+
+        match rules.upgrade {
+            WildUpgrade::JoinLargestGroup => match counts.first_mut() {
+                Some(largest) => *largest += wilds,
+                None => counts.push(wilds),
+            },
+            WildUpgrade::Unmatched => counts.extend(std::iter::repeat_n(1, wilds)),
         }
+
+        Self::for_groups(&counts)
     }
-}
 
-#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
-enum Card {
-    Joker,
-    Two,
-    Three,
-    Four,
-    Five,
-    Six,
-    Seven,
-    Eight,
-    Nine,
-    Ten,
-    Jack,
-    Queen,
-    King,
-    Ace,
-}
-
-impl Card {
-    fn for_part_1(value: char) -> Self {
-        match value {
-            '2' => Card::Two,
-            '3' => Card::Three,
-            '4' => Card::Four,
-            '5' => Card::Five,
-            '6' => Card::Six,
-            '7' => Card::Seven,
-            '8' => Card::Eight,
-            '9' => Card::Nine,
-            'T' => Card::Ten,
-            'J' => Card::Jack,
-            'Q' => Card::Queen,
-            'K' => Card::King,
-            'A' => Card::Ace,
-            _ => unreachable!(),
+    /// Groups of matching cards, largest first. Only the two largest groups count.
+    fn for_groups(counts: &[usize]) -> Category {
+        let largest = counts.first().copied().unwrap_or(0);
+        let second = counts.get(1).copied().unwrap_or(0);
+
+        match (largest, second) {
+            (5.., _) => Category::Quints,
+            (4, _) => Category::Quads,
+            (3, 2..) => Category::Boat,
+            (3, _) => Category::Trips,
+            (2, 2) => Category::TwoPair,
+            (2, _) => Category::OnePair,
+            _ => Category::HighCard,
         }
     }
+}
 
-    fn for_part_2(value: char) -> Self {
-        match value {
-            '2' => Card::Two,
-            '3' => Card::Three,
-            '4' => Card::Four,
-            '5' => Card::Five,
-            '6' => Card::Six,
-            '7' => Card::Seven,
-            '8' => Card::Eight,
-            '9' => Card::Nine,
-            'T' => Card::Ten,
-            'J' => Card::Joker,
-            'Q' => Card::Queen,
-            'K' => Card::King,
-            'A' => Card::Ace,
-            _ => unreachable!(),
-        }
+#[test]
+fn test_parts() {
+    let input = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+    assert_eq!(6440, solve_part1(input));
+    assert_eq!(5905, solve_part2(input));
+}
+
+#[test]
+fn test_category_transitions() {
+    use Category::*;
+
+    // Each hand with the category it has when J is a jack, and when J is a wild joker
+    let transitions = [
+        ("23456", HighCard, HighCard),
+        ("2345J", HighCard, OnePair),
+        ("234JJ", OnePair, Trips),
+        ("23JJJ", Trips, Quads),
+        ("2JJJJ", Quads, Quints),
+        ("JJJJJ", Quints, Quints),
+        ("22345", OnePair, OnePair),
+        ("2234J", OnePair, Trips),
+        ("223JJ", TwoPair, Quads),
+        ("22JJJ", Boat, Quints),
+        ("22335", TwoPair, TwoPair),
+        ("2233J", TwoPair, Boat),
+        ("22234", Trips, Trips),
+        ("2223J", Trips, Quads),
+        ("222JJ", Boat, Quints),
+        ("22233", Boat, Boat),
+        ("22223", Quads, Quads),
+        ("2222J", Quads, Quints),
+        ("22222", Quints, Quints),
+    ];
+
+    for (cards, standard, jokers) in transitions {
+        assert_eq!(
+            standard,
+            Category::for_cards(cards, &RuleSet::STANDARD),
+            "{}",
+            cards
+        );
+        assert_eq!(
+            jokers,
+            Category::for_cards(cards, &RuleSet::JOKERS_WILD),
+            "{}",
+            cards
+        );
     }
 }
+
+#[test]
+fn test_custom_rules() {
+    let unmatched = RuleSet {
+        upgrade: WildUpgrade::Unmatched,
+        ..RuleSet::JOKERS_WILD
+    };
+    assert_eq!(Category::HighCard, Category::for_cards("JJ234", &unmatched));
+    assert_eq!(Category::OnePair, Category::for_cards("J2234", &unmatched));
+
+    let three_cards = RuleSet {
+        hand_size: 3,
+        ..RuleSet::JOKERS_WILD
+    };
+    assert_eq!(Category::Trips, Category::for_cards("2JJ", &three_cards));
+    assert_eq!(Category::OnePair, Category::for_cards("23J", &three_cards));
+    assert_eq!(13, total_winnings("23J 1\n234 2\n2JJ 3", &three_cards));
+
+    let seven_cards = RuleSet {
+        hand_size: 7,
+        ..RuleSet::STANDARD
+    };
+    assert_eq!(
+        Category::Quints,
+        Category::for_cards("2222223", &seven_cards)
+    );
+    assert_eq!(Category::Boat, Category::for_cards("2223344", &seven_cards));
+    assert_eq!(
+        Category::TwoPair,
+        Category::for_cards("2233456", &seven_cards)
+    );
+}
+
+#[test]
+fn test_explain_hand() {
+    let explanation = explain_hand("KTJJT", &RuleSet::JOKERS_WILD);
+
+    assert_eq!(Category::Quads, explanation.category);
+    assert_eq!(vec![('T', 2), ('J', 2), ('K', 1)], explanation.counts);
+    assert_eq!(vec![(2, Some('T')), (3, Some('T'))], explanation.wilds);
+    assert_eq!(vec![11, 9, 0, 0, 9], explanation.tie_break);
+    assert_eq!(
+        "KTJJT: Quads
+  counts: Tx2 Jx2 Kx1
+  wild: #3 as T, #4 as T
+  tie-break: [11, 9, 0, 0, 9]",
+        explanation.to_string()
+    );
+
+    let explanation = explain_hand("KTJJT", &RuleSet::STANDARD);
+    assert_eq!(Category::TwoPair, explanation.category);
+    assert!(explanation.wilds.is_empty());
+
+    // A tie between groups goes to the stronger card, and an all-wild hand copies the best card
+    assert_eq!(
+        vec![(4, Some('Q'))],
+        explain_hand("Q3Q3J", &RuleSet::JOKERS_WILD).wilds
+    );
+    assert_eq!(Some('A'), RuleSet::JOKERS_WILD.wild_target("JJJJJ"));
+}
+
+#[test]
+fn test_ranked_hands() {
+    let input = "32T3K 765
+T55J5 684
+KK677 28
+KTJJT 220
+QQQJA 483";
+
+    let ranked = ranked_hands(input, &RuleSet::JOKERS_WILD);
+    let lines: Vec<_> = ranked.lines().collect();
+
+    assert_eq!(6, lines.len());
+    assert_eq!("    1 32T3K OnePair    765      765", lines[0]);
+    assert_eq!("    5 KTJJT Quads      220     1100", lines[4]);
+    assert_eq!("Total: 5905", lines[5]);
+}
@@ -10,80 +10,247 @@ fn solve_part1(input: &str) -> usize {
     network.run("AAA", |key| key == "ZZZ", moves)
 }
 
+#[aoc(day8, part1, Strings)]
+fn solve_part1_strings(input: &str) -> usize {
+    let (moves_str, network_str) = input.split_once("\n\n").unwrap();
+    let moves = Moves::from(moves_str);
+    let network = StringNetwork::from(network_str);
+
+    network.run("AAA", |key| key == "ZZZ", moves)
+}
+
 #[aoc(day8, part2)]
 fn solve_part2(input: &str) -> usize {
     let (moves_str, network_str) = input.split_once("\n\n").unwrap();
     let moves = Moves::from(moves_str);
     let network = Network::from(network_str);
 
-    // Note: In the general case, we wouldn't be able to assume that the navigation is periodic
-    // and doesn't have a prefix. However, from inspection of the results, each of the starting
-    // positions _does_ turn out to have an exactly periodic cycle so we can simplify the math
-    // of finding the end point to be finding the lowest common multiple of all of the periods
-    network
+    let cycles: Vec<_> = network
         .nodes()
         .filter(|n| n.ends_with('A'))
-        .map(|start| network.run(start, |key| key.ends_with('Z'), moves.clone()))
-        .reduce(lowest_common_multiple)
-        .unwrap()
+        .map(|start| network.ghost_cycle(start, |key| key.ends_with('Z'), moves.clone()))
+        .collect();
+
+    first_common_end(&cycles).unwrap()
 }
 
-fn lowest_common_multiple(a: usize, b: usize) -> usize {
-    (a / greatest_common_divisor(a, b)) * b
+struct GhostCycle {
+    /// The step at which the repeating part of the path begins
+    start: usize,
+    length: usize,
+    /// Steps on an end node, up to the end of the first pass through the cycle
+    hits: Vec<usize>,
 }
 
-fn greatest_common_divisor(mut a: usize, mut b: usize) -> usize {
-    if b > a {
-        std::mem::swap(&mut a, &mut b);
+impl GhostCycle {
+    fn at_end(&self, step: usize) -> bool {
+        if step < self.start {
+            self.hits.contains(&step)
+        } else {
+            let equivalent = self.start + (step - self.start) % self.length;
+            self.hits.contains(&equivalent)
+        }
     }
 
-    loop {
-        let r = a % b;
-        if r == 0 {
-            break b;
-        }
-        a = b;
-        b = r;
+    fn cycle_hits(&self) -> impl Iterator<Item = usize> + '_ {
+        self.hits.iter().copied().filter(|hit| *hit >= self.start)
+    }
+}
+
+fn first_common_end(cycles: &[GhostCycle]) -> Option<usize> {
+    let lead_in = cycles.iter().map(|cycle| cycle.start).max()?;
+
+    // Before every ghost is inside its cycle, check each step directly
+    if let Some(step) = (0..lead_in).find(|step| cycles.iter().all(|cycle| cycle.at_end(*step))) {
+        return Some(step);
     }
+
+    // Afterwards, each end node hit in a cycle gives a congruence for the step, so every
+    // combination of hits is a system of congruences to solve
+    let mut solutions = vec![(0, 1)];
+
+    for cycle in cycles {
+        solutions = solutions
+            .iter()
+            .flat_map(|solution| {
+                cycle
+                    .cycle_hits()
+                    .filter_map(|hit| {
+                        combine_congruences(*solution, (hit as i128, cycle.length as i128))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+    }
+
+    solutions
+        .into_iter()
+        .map(|(remainder, modulus)| {
+            let lead_in = lead_in as i128;
+            let step = if remainder >= lead_in {
+                remainder
+            } else {
+                remainder + (lead_in - remainder + modulus - 1) / modulus * modulus
+            };
+
+            step as usize
+        })
+        .min()
 }
 
+/// Generalised CRT, so the moduli don't need to be coprime
+fn combine_congruences((a1, m1): (i128, i128), (a2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (gcd, inverse, _) = extended_gcd(m1, m2);
+
+    if (a2 - a1) % gcd != 0 {
+        return None;
+    }
+
+    let modulus = m1 / gcd * m2;
+    let multiplier = ((a2 - a1) / gcd * inverse).rem_euclid(m2 / gcd);
+    let remainder = (a1 + m1 * multiplier).rem_euclid(modulus);
+
+    Some((remainder, modulus))
+}
+
+/// Returns (gcd, x, y) such that a * x + b * y = gcd
+fn extended_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (gcd, x, y) = extended_gcd(b, a % b);
+        (gcd, y, x - (a / b) * y)
+    }
+}
+
+/// A set bit for each move to the right
 #[derive(Clone)]
 struct Moves {
-    list: Vec<Direction>,
+    bits: Vec<u64>,
+    len: usize,
     index: usize,
 }
 
 impl Moves {
-    fn next(&mut self) -> Direction {
-        let result = self.list[self.index];
-        self.index = (self.index + 1) % self.list.len();
+    fn next(&mut self) -> usize {
+        let side = (self.bits[self.index / 64] >> (self.index % 64)) & 1;
 
-        result
+        self.index += 1;
+        if self.index == self.len {
+            self.index = 0;
+        }
+
+        side as usize
     }
 }
 
 impl From<&str> for Moves {
     fn from(value: &str) -> Self {
-        let list = value.chars().map(Direction::from).collect();
+        let mut bits = vec![0; value.len().div_ceil(64)];
 
-        Self { list, index: 0 }
+        for (index, chr) in value.chars().enumerate() {
+            match chr {
+                'L' => {}
+                'R' => bits[index / 64] |= 1 << (index % 64),
+                _ => unreachable!(),
+            }
+        }
+
+        Self {
+            bits,
+            len: value.len(),
+            index: 0,
+        }
     }
 }
 
-#[derive(Clone, Copy)]
-enum Direction {
-    Left,
-    Right,
+/// Node names interned to dense indices
+struct Network<'a> {
+    names: Vec<&'a str>,
+    ids: HashMap<&'a str, u32>,
+    edges: Vec<[u32; 2]>,
 }
 
-impl From<char> for Direction {
-    fn from(value: char) -> Self {
-        match value {
-            'L' => Direction::Left,
-            'R' => Direction::Right,
-            _ => unreachable!(),
+impl<'a> Network<'a> {
+    fn ends<P>(&self, at_end: P) -> Vec<bool>
+    where
+        P: Fn(&'a str) -> bool,
+    {
+        self.names.iter().map(|name| at_end(name)).collect()
+    }
+
+    fn run<P>(&self, start: &'a str, at_end: P, mut moves: Moves) -> usize
+    where
+        P: Fn(&'a str) -> bool,
+    {
+        let ends = self.ends(at_end);
+        let mut current = self.ids[start] as usize;
+        let mut count = 0;
+
+        while !ends[current] {
+            current = self.edges[current][moves.next()] as usize;
+            count += 1;
+        }
+
+        count
+    }
+
+    fn ghost_cycle<P>(&self, start: &'a str, at_end: P, mut moves: Moves) -> GhostCycle
+    where
+        P: Fn(&'a str) -> bool,
+    {
+        let ends = self.ends(at_end);
+        let mut seen = vec![None; self.names.len() * moves.len];
+        let mut hits = Vec::new();
+        let mut current = self.ids[start] as usize;
+        let mut step = 0;
+
+        loop {
+            let state = current * moves.len + moves.index;
+            if let Some(first) = seen[state] {
+                return GhostCycle {
+                    start: first,
+                    length: step - first,
+                    hits,
+                };
+            }
+            seen[state] = Some(step);
+
+            if ends[current] {
+                hits.push(step);
+            }
+
+            current = self.edges[current][moves.next()] as usize;
+            step += 1;
         }
     }
+
+    fn nodes(&self) -> impl Iterator<Item = &'a str> + '_ {
+        self.names.iter().copied()
+    }
+}
+
+impl<'a> From<&'a str> for Network<'a> {
+    fn from(value: &'a str) -> Self {
+        let names: Vec<_> = value.lines().map(|line| &line[0..3]).collect();
+        let ids: HashMap<_, _> = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| (*name, id as u32))
+            .collect();
+
+        let edges = value
+            .lines()
+            .map(|line| {
+                let (_, edge_str) = line.split_once(" = ").unwrap();
+                let edge = Edges::from(edge_str);
+
+                [ids[edge.left], ids[edge.right]]
+            })
+            .collect();
+
+        Self { names, ids, edges }
+    }
 }
 
 struct Edges<'a> {
@@ -100,11 +267,11 @@ impl<'a> From<&'a str> for Edges<'a> {
     }
 }
 
-struct Network<'a> {
+struct StringNetwork<'a> {
     edges: HashMap<&'a str, Edges<'a>>,
 }
 
-impl<'a> Network<'a> {
+impl<'a> StringNetwork<'a> {
     fn run<P>(&self, start: &'a str, at_end: P, mut moves: Moves) -> usize
     where
         P: Fn(&'a str) -> bool,
@@ -114,21 +281,17 @@ impl<'a> Network<'a> {
 
         while !at_end(current) {
             current = match moves.next() {
-                Direction::Left => self.edges[current].left,
-                Direction::Right => self.edges[current].right,
+                0 => self.edges[current].left,
+                _ => self.edges[current].right,
             };
             count += 1;
         }
 
         count
     }
-
-    fn nodes(&self) -> impl Iterator<Item = &str> {
-        self.edges.keys().copied()
-    }
 }
 
-impl<'a> From<&'a str> for Network<'a> {
+impl<'a> From<&'a str> for StringNetwork<'a> {
     fn from(value: &'a str) -> Self {
         let edges = value
             .lines()
@@ -141,3 +304,80 @@ impl<'a> From<&'a str> for Network<'a> {
         Self { edges }
     }
 }
+
+#[test]
+fn test_part1() {
+    let input = "LLR
+
+AAA = (BBB, BBB)
+BBB = (AAA, ZZZ)
+ZZZ = (ZZZ, ZZZ)";
+
+    assert_eq!(6, solve_part1(input));
+    assert_eq!(6, solve_part1_strings(input));
+}
+
+#[test]
+fn test_moves() {
+    let list = "LRR".repeat(25);
+    let mut moves = Moves::from(list.as_str());
+
+    for chr in list.chars().cycle().take(list.len() * 2) {
+        assert_eq!(usize::from(chr == 'R'), moves.next());
+    }
+}
+
+#[test]
+fn test_part2() {
+    let input = "LR
+
+11A = (11B, XXX)
+11B = (XXX, 11Z)
+11Z = (11B, XXX)
+22A = (22B, XXX)
+22B = (22C, 22C)
+22C = (22Z, 22Z)
+22Z = (22B, 22B)
+XXX = (XXX, XXX)";
+
+    assert_eq!(6, solve_part2(input));
+}
+
+#[test]
+fn test_part2_with_lead_in() {
+    // The first ghost is at an end node on every even step from 2, and the second on steps 5,
+    // 8, 11, ... so the lowest common multiple of the first hits (10) is wrong
+    let input = "L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11B, 11B)
+22A = (22B, 22B)
+22B = (22C, 22C)
+22C = (22D, 22D)
+22D = (22E, 22E)
+22E = (22Z, 22Z)
+22Z = (22D, 22D)";
+
+    assert_eq!(8, solve_part2(input));
+
+    // An end node that is only visited before the ghost enters its cycle
+    let input = "L
+
+11A = (11B, 11B)
+11B = (11Z, 11Z)
+11Z = (11B, 11B)
+33A = (33B, 33B)
+33B = (33Z, 33Z)
+33Z = (33C, 33C)
+33C = (33C, 33C)";
+
+    assert_eq!(2, solve_part2(input));
+}
+
+#[test]
+fn test_combine_congruences() {
+    assert_eq!(Some((2, 6)), combine_congruences((0, 2), (2, 3)));
+    assert_eq!(Some((10, 12)), combine_congruences((2, 4), (4, 6)));
+    assert_eq!(None, combine_congruences((1, 4), (2, 6)));
+}
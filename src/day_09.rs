@@ -1,63 +1,267 @@
+use crate::geometry::gcd;
+use crate::stream;
 use aoc_runner_derive::aoc;
+use std::fmt;
+use std::io::{self, BufRead};
 
 #[aoc(day9, part1)]
-fn solve_part1(input: &str) -> isize {
+fn solve_part1(input: &str) -> i128 {
     input
         .lines()
-        .map(|line| {
-            let sequence: Vec<_> = line.split(' ').map(|n| n.parse().unwrap()).collect();
-            next_value(&sequence)
-        })
+        .map(|line| Sequence::from(line).forward(1).unwrap())
         .sum()
 }
 
 #[aoc(day9, part2)]
-fn solve_part2(input: &str) -> isize {
+fn solve_part2(input: &str) -> i128 {
     input
         .lines()
-        .map(|line| {
-            let sequence: Vec<_> = line.split(' ').map(|n| n.parse().unwrap()).collect();
-            previous_value(&sequence)
-        })
+        .map(|line| Sequence::from(line).backward(1).unwrap())
         .sum()
 }
 
-fn next_value(sequence: &[isize]) -> isize {
-    if sequence.len() <= 1 {
-        return 0;
+pub fn stream_part1<R: BufRead>(reader: R) -> io::Result<i128> {
+    stream::fold_lines(reader, 0, |total, line| {
+        total + Sequence::from(line).forward(1).unwrap()
+    })
+}
+
+pub fn stream_part2<R: BufRead>(reader: R) -> io::Result<i128> {
+    stream::fold_lines(reader, 0, |total, line| {
+        total + Sequence::from(line).backward(1).unwrap()
+    })
+}
+
+/// A sequence and its difference table, extrapolated with Newton's forward difference formula
+pub struct Sequence {
+    len: usize,
+    /// The first entry of each row of the difference table
+    leading: Vec<i128>,
+}
+
+impl Sequence {
+    pub fn new(values: &[i128]) -> Option<Self> {
+        let mut leading = Vec::with_capacity(values.len());
+        let mut row = values.to_vec();
+
+        // Each row of the table overwrites the one above it, so only one buffer is needed
+        for level in 0..values.len() {
+            let current = &mut row[..values.len() - level];
+            if current.iter().all(|value| *value == 0) {
+                break;
+            }
+
+            leading.push(current[0]);
+            for index in 0..current.len() - 1 {
+                current[index] = current[index + 1].checked_sub(current[index])?;
+            }
+        }
+
+        Some(Self {
+            len: values.len(),
+            leading,
+        })
+    }
+
+    pub fn degree(&self) -> usize {
+        self.leading.len().saturating_sub(1)
+    }
+
+    /// The value at `position`, where the first value is at position 0
+    pub fn value_at(&self, position: i128) -> Option<i128> {
+        let mut total: i128 = 0;
+        let mut binomial: i128 = 1;
+
+        for (row, difference) in self.leading.iter().enumerate() {
+            if row > 0 {
+                // C(x, j) = C(x, j - 1) * (x - j + 1) / j, which always divides exactly
+                let row = row as i128;
+                binomial = binomial.checked_mul(position.checked_sub(row - 1)?)? / row;
+            }
+
+            total = total.checked_add(difference.checked_mul(binomial)?)?;
+        }
+
+        Some(total)
+    }
+
+    pub fn forward(&self, steps: usize) -> Option<i128> {
+        self.value_at((self.len as i128 - 1).checked_add(steps as i128)?)
+    }
+
+    pub fn backward(&self, steps: usize) -> Option<i128> {
+        self.value_at(-(steps as i128))
+    }
+
+    pub fn polynomial(&self) -> Option<Polynomial> {
+        let degree = self.degree();
+        let mut denominator: i128 = 1;
+        for factor in 2..=degree as i128 {
+            denominator = denominator.checked_mul(factor)?;
+        }
+
+        // Multiplying every term through by degree! clears the j! below each C(x, j), leaving
+        // the falling factorial x(x - 1)...(x - j + 1) times degree! / j!
+        let mut coefficients = vec![0_i128; degree + 1];
+        let mut falling = vec![1_i128];
+        let mut scale = denominator;
+
+        for (row, difference) in self.leading.iter().enumerate() {
+            if row > 0 {
+                scale /= row as i128;
+
+                let mut next = vec![0_i128; falling.len() + 1];
+                for (power, coefficient) in falling.iter().enumerate() {
+                    next[power + 1] = next[power + 1].checked_add(*coefficient)?;
+                    next[power] =
+                        next[power].checked_sub(coefficient.checked_mul(row as i128 - 1)?)?;
+                }
+                falling = next;
+            }
+
+            let term = difference.checked_mul(scale)?;
+            for (power, coefficient) in falling.iter().enumerate() {
+                coefficients[power] =
+                    coefficients[power].checked_add(coefficient.checked_mul(term)?)?;
+            }
+        }
+
+        let divisor = coefficients
+            .iter()
+            .fold(denominator, |divisor, coefficient| {
+                gcd(divisor, coefficient.abs())
+            });
+
+        Some(Polynomial {
+            coefficients: coefficients.iter().map(|c| c / divisor).collect(),
+            denominator: denominator / divisor,
+        })
     }
+}
 
-    let mut diffs = Vec::with_capacity(sequence.len() - 1);
-    for i in 1..sequence.len() {
-        diffs.push(sequence[i] - sequence[i - 1]);
+impl From<&str> for Sequence {
+    fn from(line: &str) -> Self {
+        let values: Vec<i128> = line.split(' ').map(|n| n.parse().unwrap()).collect();
+        Self::new(&values).unwrap()
     }
+}
+
+/// Integer coefficients over a common denominator, in order of increasing power
+#[derive(Debug, PartialEq, Eq)]
+pub struct Polynomial {
+    pub coefficients: Vec<i128>,
+    pub denominator: i128,
+}
 
-    let last = *sequence.last().unwrap();
-    let diff = if diffs.iter().all(|n| *n == 0) {
-        0
-    } else {
-        next_value(&diffs)
-    };
+impl Polynomial {
+    pub fn evaluate(&self, x: i128) -> Option<i128> {
+        let mut total: i128 = 0;
+        for coefficient in self.coefficients.iter().rev() {
+            total = total.checked_mul(x)?.checked_add(*coefficient)?;
+        }
 
-    last + diff
+        Some(total / self.denominator)
+    }
 }
 
-fn previous_value(sequence: &[isize]) -> isize {
-    if sequence.len() <= 1 {
-        return 0;
+impl fmt::Display for Polynomial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut terms = Vec::new();
+
+        for (power, coefficient) in self.coefficients.iter().enumerate().rev() {
+            if *coefficient == 0 {
+                continue;
+            }
+
+            let sign = if *coefficient < 0 { "-" } else { "+" };
+            let magnitude = coefficient.unsigned_abs();
+            let term = match (power, magnitude) {
+                (0, _) => magnitude.to_string(),
+                (1, 1) => "x".to_string(),
+                (1, _) => format!("{}x", magnitude),
+                (_, 1) => format!("x^{}", power),
+                _ => format!("{}x^{}", magnitude, power),
+            };
+
+            terms.push((sign, term));
+        }
+
+        let mut text = String::new();
+        for (index, (sign, term)) in terms.iter().enumerate() {
+            match (index, *sign) {
+                (0, "-") => text.push('-'),
+                (0, _) => {}
+                _ => text.push_str(&format!(" {} ", sign)),
+            }
+            text.push_str(term);
+        }
+
+        if text.is_empty() {
+            text.push('0');
+        }
+
+        if self.denominator == 1 {
+            write!(f, "{}", text)
+        } else {
+            write!(f, "({}) / {}", text, self.denominator)
+        }
     }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "0 3 6 9 12 15
+1 3 6 10 15 21
+10 13 16 21 30 45";
+
+#[test]
+fn test_streaming() {
+    assert_eq!(114, solve_part1(EXAMPLE));
+    assert_eq!(114, stream_part1(EXAMPLE.as_bytes()).unwrap());
+    assert_eq!(2, solve_part2(EXAMPLE));
+    assert_eq!(2, stream_part2(EXAMPLE.as_bytes()).unwrap());
 
-    let mut diffs = Vec::with_capacity(sequence.len() - 1);
-    for i in 1..sequence.len() {
-        diffs.push(sequence[i] - sequence[i - 1]);
+    let reader = io::BufReader::new(stream::Repeat::new("10 13 16 21 30 45\n", 20_000));
+    assert_eq!(5 * 20_000, stream_part2(reader).unwrap());
+}
+
+#[test]
+fn test_sequence() {
+    let linear = Sequence::from("0 3 6 9 12 15");
+    assert_eq!(1, linear.degree());
+    assert_eq!(Some(24), linear.forward(3));
+    assert_eq!(Some(-6), linear.backward(2));
+    assert_eq!("3x", linear.polynomial().unwrap().to_string());
+
+    let triangular = Sequence::from("1 3 6 10 15 21");
+    assert_eq!(2, triangular.degree());
+    let polynomial = triangular.polynomial().unwrap();
+    assert_eq!("(x^2 + 3x + 2) / 2", polynomial.to_string());
+    for x in -5..20 {
+        assert_eq!(triangular.value_at(x), polynomial.evaluate(x));
     }
 
-    let first = sequence[0];
-    let diff = if diffs.iter().all(|n| *n == 0) {
-        0
-    } else {
-        previous_value(&diffs)
-    };
+    let cubic = Sequence::from("10 13 16 21 30 45");
+    assert_eq!(3, cubic.degree());
+    assert_eq!(Some(68), cubic.forward(1));
+    assert_eq!(Some(5), cubic.backward(1));
+    assert_eq!(cubic.forward(10), cubic.polynomial().unwrap().evaluate(15));
+
+    assert_eq!(0, Sequence::from("0 0 0").degree());
+    assert_eq!(
+        "0",
+        Sequence::from("0 0 0").polynomial().unwrap().to_string()
+    );
+    assert_eq!(
+        "-7",
+        Sequence::from("-7 -7").polynomial().unwrap().to_string()
+    );
+}
+
+#[test]
+fn test_overflow() {
+    assert!(Sequence::new(&[i128::MIN, i128::MAX]).is_none());
 
-    first - diff
+    let huge = Sequence::new(&[0, i128::MAX / 2]).unwrap();
+    assert_eq!(Some(i128::MAX / 2), huge.value_at(1));
+    assert_eq!(None, huge.forward(2));
 }
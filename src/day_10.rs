@@ -1,27 +1,46 @@
+use crate::geometry::Polygon;
 use aoc_runner_derive::aoc;
 use std::collections::HashSet;
 
 #[aoc(day10, part1)]
 fn solve_part1(input: &str) -> usize {
     let (zone, start) = parse_zone(input);
-    // Direction determined from manual inspection of input
-    let points = zone.traverse_loop(start, Direction::Down);
+    let points = zone.traverse_loop(start);
 
     points.len() / 2
 }
 
 #[aoc(day10, part2)]
-fn solve_part2(input: &str) -> usize {
+fn solve_part2(input: &str) -> i128 {
     let (zone, start) = parse_zone(input);
-    // Direction determined from manual inspection of input
-    let points = zone.traverse_loop(start, Direction::Down);
+    let points = zone.traverse_loop(start);
+
+    // Each loop tile is a vertex of the polygon, so the enclosed tiles are its interior points
+    let polygon = Polygon::new(
+        points
+            .iter()
+            .map(|point| (point.row as i128, point.col as i128)),
+    );
+
+    polygon.interior_points().unwrap()
+}
+
+#[aoc(day10, part2, Parity)]
+fn solve_part2_parity(input: &str) -> i128 {
+    let (zone, start) = parse_zone(input);
+    let start_tile = zone.start_tile(start);
+    let points: HashSet<_> = zone.traverse_loop(start).into_iter().collect();
     let mut contained_ground = 0;
 
     for row in 0..zone.rows {
         let mut parity = LoopParity::Out;
         for col in 0..zone.cols {
             let point = Point::new(row, col);
-            let tile = zone.tile(point);
+            let tile = if point == start {
+                start_tile
+            } else {
+                zone.tile(point)
+            };
 
             if points.contains(&point) {
                 // This tile is part of the loop and so can affect parity
@@ -48,25 +67,83 @@ impl Zone {
         self.tiles[index]
     }
 
-    fn traverse_loop(&self, start: Point, direction: Direction) -> HashSet<Point> {
+    fn neighbor(&self, point: Point, direction: Direction) -> Option<Point> {
+        let valid = match direction {
+            Direction::Up => point.row > 0,
+            Direction::Down => point.row + 1 < self.rows,
+            Direction::Left => point.col > 0,
+            Direction::Right => point.col + 1 < self.cols,
+        };
+
+        valid.then(|| point.shift(direction))
+    }
+
+    fn start_connections(&self, start: Point) -> Vec<Direction> {
+        [
+            Direction::Up,
+            Direction::Down,
+            Direction::Left,
+            Direction::Right,
+        ]
+        .into_iter()
+        .filter(|dir| {
+            self.neighbor(start, *dir)
+                .is_some_and(|point| self.tile(point).next_direction(*dir).is_some())
+        })
+        .collect()
+    }
+
+    /// The pipe shape hidden underneath the start tile
+    fn start_tile(&self, start: Point) -> Tile {
+        let (_, [first, last]) = self.find_loop(start);
+
+        match (first, last) {
+            (Direction::Up, Direction::Down) | (Direction::Down, Direction::Up) => Tile::UpDown,
+            (Direction::Up, Direction::Left) | (Direction::Left, Direction::Up) => Tile::UpLeft,
+            (Direction::Up, Direction::Right) | (Direction::Right, Direction::Up) => Tile::UpRight,
+            (Direction::Down, Direction::Left) | (Direction::Left, Direction::Down) => {
+                Tile::DownLeft
+            }
+            (Direction::Down, Direction::Right) | (Direction::Right, Direction::Down) => {
+                Tile::DownRight
+            }
+            (Direction::Left, Direction::Right) | (Direction::Right, Direction::Left) => {
+                Tile::LeftRight
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn traverse_loop(&self, start: Point) -> Vec<Point> {
+        self.find_loop(start).0
+    }
+
+    /// Other pipes may point at the start, so each connection is followed until one closes the loop
+    fn find_loop(&self, start: Point) -> (Vec<Point>, [Direction; 2]) {
+        self.start_connections(start)
+            .into_iter()
+            .find_map(|first| {
+                let (points, arrival) = self.follow(start, first)?;
+                Some((points, [first, arrival.opposite()]))
+            })
+            .expect("No loop passes through the start tile")
+    }
+
+    fn follow(&self, start: Point, first: Direction) -> Option<(Vec<Point>, Direction)> {
         let mut current = start;
-        let mut dir = direction;
-        let mut loop_points = HashSet::new();
+        let mut dir = first;
+        let mut points = Vec::new();
 
         loop {
-            loop_points.insert(current);
-
-            current = current.shift(dir);
-            let next_tile = self.tile(current);
-            dir = match next_tile.next_direction(dir) {
-                Some(d) => d,
-                None => {
-                    break;
-                }
-            };
-        }
+            points.push(current);
+
+            current = self.neighbor(current, dir)?;
+            if current == start {
+                return Some((points, dir));
+            }
 
-        loop_points
+            dir = self.tile(current).next_direction(dir)?;
+        }
     }
 }
 
@@ -115,7 +192,7 @@ impl Point {
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Direction {
     Left,
     Right,
@@ -123,6 +200,17 @@ enum Direction {
     Down,
 }
 
+impl Direction {
+    fn opposite(self) -> Self {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Tile {
     Start,
@@ -188,28 +276,105 @@ impl LoopParity {
 
     fn next(self, tile: Tile) -> Self {
         use LoopParity::*;
-        // By direct inspection, the Start tile is DownLeft
         match (self, tile) {
             // The Ground and LeftRight tiles do not change parity
             (val, Tile::Ground | Tile::LeftRight) => val,
+            // The Start tile must be replaced by the pipe underneath it
+            (_, Tile::Start) => unreachable!(),
             (Out, Tile::UpDown) => In,
             (Out, Tile::UpRight | Tile::UpLeft) => OutUp,
-            (Out, Tile::DownRight | Tile::DownLeft | Tile::Start) => OutDown,
+            (Out, Tile::DownRight | Tile::DownLeft) => OutDown,
             (OutUp, Tile::UpDown) => unreachable!(),
             (OutUp, Tile::UpRight | Tile::UpLeft) => Out,
-            (OutUp, Tile::DownRight | Tile::DownLeft | Tile::Start) => In,
+            (OutUp, Tile::DownRight | Tile::DownLeft) => In,
             (OutDown, Tile::UpDown) => unreachable!(),
             (OutDown, Tile::UpRight | Tile::UpLeft) => In,
-            (OutDown, Tile::DownRight | Tile::DownLeft | Tile::Start) => Out,
+            (OutDown, Tile::DownRight | Tile::DownLeft) => Out,
             (In, Tile::UpDown) => Out,
             (In, Tile::UpRight | Tile::UpLeft) => InUp,
-            (In, Tile::DownRight | Tile::DownLeft | Tile::Start) => InDown,
+            (In, Tile::DownRight | Tile::DownLeft) => InDown,
             (InUp, Tile::UpDown) => unreachable!(),
             (InUp, Tile::UpRight | Tile::UpLeft) => In,
-            (InUp, Tile::DownRight | Tile::DownLeft | Tile::Start) => Out,
+            (InUp, Tile::DownRight | Tile::DownLeft) => Out,
             (InDown, Tile::UpDown) => unreachable!(),
             (InDown, Tile::UpRight | Tile::UpLeft) => Out,
-            (InDown, Tile::DownRight | Tile::DownLeft | Tile::Start) => In,
+            (InDown, Tile::DownRight | Tile::DownLeft) => In,
         }
     }
 }
+
+#[test]
+fn test_part1() {
+    let input = "..F7.
+.FJ|.
+SJ.L7
+|F--J
+LJ...";
+
+    assert_eq!(8, solve_part1(input));
+}
+
+#[test]
+fn test_part2() {
+    let inputs = [
+        (
+            "...........
+.S-------7.
+.|F-----7|.
+.||.....||.
+.||.....||.
+.|L-7.F-J|.
+.|..|.|..|.
+.L--J.L--J.
+...........",
+            4,
+        ),
+        (
+            ".F----7F7F7F7F-7....
+.|F--7||||||||FJ....
+.||.FJ||||||||L7....
+FJL7L7LJLJ||LJ.L-7..
+L--J.L7...LJS7F-7L7.
+....F-J..F7FJ|L7L7L7
+....L7.F7||L7|.L7L7|
+.....|FJLJ|FJ|F7|.LJ
+....FJL-7.||.||||...
+....L---J.LJ.LJLJ...",
+            8,
+        ),
+        (
+            "FF7FSF7F7F7F7F7F---7
+L|LJ||||||||||||F--J
+FL-7LJLJ||||||LJL-77
+F--JF--7||LJLJ7F7FJ-
+L---JF-JLJ.||-FJLJJ7
+|F|F-JF---7F7-L7L|7|
+|FFJF7L7F-JF7|JL---7
+7-L-JL7||F7|L7F-7F7|
+L.L7LFJ|||||FJL7||LJ
+L7JLJL-JLJLJL--JLJ.L",
+            10,
+        ),
+    ];
+
+    for (input, expected) in inputs {
+        assert_eq!(expected, solve_part2(input));
+        assert_eq!(expected, solve_part2_parity(input));
+    }
+}
+
+#[test]
+fn test_stray_start_connections() {
+    // The pipes above and to the left of the start point at it, but the loop runs right and down
+    let input = ".|...
+-S-7.
+.|.|.
+.L-J.";
+
+    assert_eq!(4, solve_part1(input));
+    assert_eq!(1, solve_part2(input));
+    assert_eq!(1, solve_part2_parity(input));
+
+    let (zone, start) = parse_zone(input);
+    assert!(matches!(zone.start_tile(start), Tile::DownRight));
+}
@@ -1,124 +1,292 @@
 use aoc_runner_derive::aoc;
-use std::collections::{HashMap, HashSet};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 
 #[aoc(day11, part1)]
 fn solve_part1(input: &str) -> usize {
-    let mut universe = Universe::from(input);
-    universe.expand(2);
-
-    universe.shortest_paths()
+    Universe::from(input).total_distance(2)
 }
 
 #[aoc(day11, part2)]
 fn solve_part2(input: &str) -> usize {
-    let mut universe = Universe::from(input);
-    universe.expand(1_000_000);
-
-    universe.shortest_paths()
+    Universe::from(input).total_distance(1_000_000)
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-struct Point {
-    row: usize,
-    col: usize,
+/// The sum of the distances between every pair of galaxies for each expansion factor
+pub fn total_distances(input: &str, factors: &[usize]) -> Vec<usize> {
+    Universe::from(input).total_distances(factors)
 }
 
-impl Point {
-    fn new(row: usize, col: usize) -> Self {
-        Point { row, col }
-    }
+/// The galaxies and the lines they occupy, since the empty lines follow from the gaps
+/// Galaxies are numbered from 1 in reading order.
+pub struct Universe {
+    galaxies: Vec<Point>,
+    rows: Axis,
+    cols: Axis,
+    height: usize,
+    width: usize,
+}
 
-    fn distance(self, other: Point) -> usize {
-        self.row.abs_diff(other.row) + self.col.abs_diff(other.col)
+impl Universe {
+    pub fn galaxy_count(&self) -> usize {
+        self.galaxies.len()
     }
-}
 
-struct Universe {
-    galaxies: HashSet<Point>,
-    rows: usize,
-    cols: usize,
-}
+    pub fn position(&self, galaxy: usize, factor: usize) -> Option<(usize, usize)> {
+        assert!(factor >= 1, "Expansion factor must be at least 1");
+        let point = self.galaxies.get(galaxy.checked_sub(1)?)?;
 
-impl Universe {
-    fn has_galaxy(&self, row: usize, col: usize) -> bool {
-        self.galaxies.contains(&Point::new(row, col))
+        Some((
+            self.rows.expand(point.row, factor),
+            self.cols.expand(point.col, factor),
+        ))
     }
 
-    fn expand(&mut self, factor: usize) {
-        let mut expansion = 0;
-        let mut col_map = HashMap::new();
+    pub fn distance(&self, first: usize, second: usize, factor: usize) -> Option<usize> {
+        let (first_row, first_col) = self.position(first, factor)?;
+        let (second_row, second_col) = self.position(second, factor)?;
 
-        for col in 0..self.cols {
-            col_map.insert(col, col + expansion);
+        Some(first_row.abs_diff(second_row) + first_col.abs_diff(second_col))
+    }
 
-            let is_empty = (0..self.rows).all(|row| !self.has_galaxy(row, col));
+    /// The `k` nearest pairs as `(distance, first, second)`, ties broken by galaxy number
+    pub fn closest_pairs(&self, k: usize, factor: usize) -> Vec<(usize, usize, usize)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
 
-            if is_empty {
-                expansion += factor - 1;
+        for pair in self.pairs(factor) {
+            heap.push(pair);
+            if heap.len() > k {
+                heap.pop();
             }
         }
 
-        let new_cols = self.cols + expansion;
-        expansion = 0;
-        let mut row_map = HashMap::new();
-
-        for row in 0..self.rows {
-            row_map.insert(row, row + expansion);
+        heap.into_sorted_vec()
+    }
 
-            let is_empty = (0..self.cols).all(|col| !self.has_galaxy(row, col));
+    /// The `k` farthest pairs as `(distance, first, second)`, ties broken by galaxy number
+    pub fn farthest_pairs(&self, k: usize, factor: usize) -> Vec<(usize, usize, usize)> {
+        let mut heap = BinaryHeap::with_capacity(k + 1);
 
-            if is_empty {
-                expansion += factor - 1;
+        for (distance, first, second) in self.pairs(factor) {
+            heap.push((Reverse(distance), first, second));
+            if heap.len() > k {
+                heap.pop();
             }
         }
 
-        let new_rows = self.rows + expansion;
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|(Reverse(distance), first, second)| (distance, first, second))
+            .collect()
+    }
 
-        let new_galaxies = self
-            .galaxies
-            .iter()
-            .map(|point| Point::new(row_map[&point.row], col_map[&point.col]))
+    fn pairs(&self, factor: usize) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        let positions: Vec<_> = (1..=self.galaxies.len())
+            .map(|galaxy| self.position(galaxy, factor).unwrap())
             .collect();
 
-        self.galaxies = new_galaxies;
-        self.rows = new_rows;
-        self.cols = new_cols;
+        let count = positions.len();
+
+        (0..count)
+            .flat_map(move |first| (first + 1..count).map(move |second| (first, second)))
+            .map(move |(first, second)| {
+                let (first_row, first_col) = positions[first];
+                let (second_row, second_col) = positions[second];
+                let distance = first_row.abs_diff(second_row) + first_col.abs_diff(second_col);
+
+                (distance, first + 1, second + 1)
+            })
     }
 
-    fn shortest_paths(&self) -> usize {
-        let mut total = 0;
+    /// Every empty line is drawn, so this is only suitable for small factors
+    pub fn render(&self, factor: usize) -> String {
+        assert!(factor >= 1, "Expansion factor must be at least 1");
+        let height = self.rows.expand(self.height, factor);
+        let width = self.cols.expand(self.width, factor);
+        let mut cells = vec![vec!['.'; width]; height];
 
-        for a in &self.galaxies {
-            for b in &self.galaxies {
-                total += a.distance(*b);
-            }
+        for galaxy in 1..=self.galaxies.len() {
+            let (row, col) = self.position(galaxy, factor).unwrap();
+            cells[row][col] = '#';
         }
 
-        // Note, this double counts all the pairs
-        total / 2
+        cells
+            .into_iter()
+            .map(|row| row.into_iter().collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn total_distance(&self, factor: usize) -> usize {
+        self.total_distances(&[factor])[0]
+    }
+
+    /// Every empty line passed adds `factor - 1`, so the pairwise sums are shared by every factor
+    pub fn total_distances(&self, factors: &[usize]) -> Vec<usize> {
+        assert!(
+            factors.iter().all(|factor| *factor >= 1),
+            "Expansion factor must be at least 1"
+        );
+        let mut rows: Vec<_> = self.galaxies.iter().map(|galaxy| galaxy.row).collect();
+        let mut cols: Vec<_> = self.galaxies.iter().map(|galaxy| galaxy.col).collect();
+        rows.sort_unstable();
+        cols.sort_unstable();
+
+        let unexpanded = pairwise_sum(&rows) + pairwise_sum(&cols);
+        let empty_rows: Vec<_> = rows
+            .iter()
+            .map(|row| self.rows.empty_before(*row))
+            .collect();
+        let empty_cols: Vec<_> = cols
+            .iter()
+            .map(|col| self.cols.empty_before(*col))
+            .collect();
+        let empty = pairwise_sum(&empty_rows) + pairwise_sum(&empty_cols);
+
+        factors
+            .iter()
+            .map(|factor| unexpanded + (factor - 1) * empty)
+            .collect()
     }
 }
 
 impl From<&str> for Universe {
     fn from(value: &str) -> Self {
-        let mut rows = 0;
-        let mut cols = 0;
-        let mut galaxies = HashSet::new();
-
-        for (row, line) in value.lines().enumerate() {
-            for (col, chr) in line.chars().enumerate() {
-                if chr == '#' {
-                    galaxies.insert(Point::new(row, col));
-                }
-                cols = cols.max(col + 1);
-            }
-            rows = rows.max(row + 1);
-        }
+        let galaxies: Vec<_> = value
+            .lines()
+            .enumerate()
+            .flat_map(|(row, line)| {
+                line.char_indices()
+                    .filter(|(_, chr)| *chr == '#')
+                    .map(move |(col, _)| Point::new(row, col))
+            })
+            .collect();
 
-        Universe {
+        Self {
+            rows: Axis::new(galaxies.iter().map(|galaxy| galaxy.row)),
+            cols: Axis::new(galaxies.iter().map(|galaxy| galaxy.col)),
             galaxies,
-            rows,
-            cols,
+            height: value.lines().count(),
+            width: value.lines().map(str::len).max().unwrap_or(0),
         }
     }
 }
+
+struct Axis {
+    occupied: Vec<usize>,
+}
+
+impl Axis {
+    fn new<I: Iterator<Item = usize>>(positions: I) -> Self {
+        let mut occupied: Vec<_> = positions.collect();
+        occupied.sort_unstable();
+        occupied.dedup();
+
+        Self { occupied }
+    }
+
+    fn empty_before(&self, position: usize) -> usize {
+        position
+            - self
+                .occupied
+                .partition_point(|occupied| *occupied < position)
+    }
+
+    fn expand(&self, position: usize, factor: usize) -> usize {
+        position + (factor - 1) * self.empty_before(position)
+    }
+}
+
+/// The sum of the differences between every pair of values, which must be sorted
+fn pairwise_sum(sorted: &[usize]) -> usize {
+    let mut total = 0;
+    let mut prefix = 0;
+
+    for (index, value) in sorted.iter().enumerate() {
+        total += value * index - prefix;
+        prefix += value;
+    }
+
+    total
+}
+
+#[derive(Clone, Copy)]
+struct Point {
+    row: usize,
+    col: usize,
+}
+
+impl Point {
+    fn new(row: usize, col: usize) -> Self {
+        Point { row, col }
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "...#......
+.......#..
+#.........
+..........
+......#...
+.#........
+.........#
+..........
+.......#..
+#...#.....";
+
+#[test]
+fn test_part1() {
+    assert_eq!(374, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_many_factors() {
+    assert_eq!(
+        vec![292, 374, 1030, 8410],
+        total_distances(EXAMPLE, &[1, 2, 10, 100])
+    );
+}
+
+#[test]
+fn test_pairs() {
+    let universe = Universe::from(EXAMPLE);
+
+    assert_eq!(9, universe.galaxy_count());
+    // The distances given in the puzzle text
+    assert_eq!(Some(9), universe.distance(5, 9, 2));
+    assert_eq!(Some(15), universe.distance(1, 7, 2));
+    assert_eq!(Some(17), universe.distance(3, 6, 2));
+    assert_eq!(Some(5), universe.distance(8, 9, 2));
+    assert_eq!(None, universe.distance(0, 9, 2));
+    assert_eq!(None, universe.distance(1, 10, 2));
+
+    let closest = universe.closest_pairs(3, 2);
+    assert_eq!(vec![(5, 2, 4), (5, 3, 5), (5, 7, 9)], closest);
+    let farthest = universe.farthest_pairs(2, 2);
+    assert_eq!(vec![(19, 2, 8), (17, 3, 6)], farthest);
+}
+
+#[test]
+fn test_render() {
+    // The expanded universe from the puzzle text
+    let expected = "....#........
+.........#...
+#............
+.............
+.............
+........#....
+.#...........
+............#
+.............
+.............
+.........#...
+#....#.......";
+
+    assert_eq!(expected, Universe::from(EXAMPLE).render(2));
+}
+
+#[test]
+#[should_panic(expected = "Expansion factor must be at least 1")]
+fn test_zero_factor() {
+    total_distances(EXAMPLE, &[2, 0]);
+}
@@ -1,12 +1,10 @@
 use aoc_runner_derive::aoc;
-use std::collections::HashMap;
 
 #[aoc(day12, part1)]
 fn solve_part1(input: &str) -> usize {
     input
         .lines()
-        .map(parse_row)
-        .map(|(row, expected)| row.count_matches(&expected))
+        .map(|line| ArrangementTable::new(&Record::from(line)).count())
         .sum()
 }
 
@@ -14,45 +12,44 @@ fn solve_part1(input: &str) -> usize {
 fn solve_part2(input: &str) -> usize {
     input
         .lines()
-        .map(parse_row)
-        .map(|(row, expected)| {
-            let mut more_expected = Vec::with_capacity(5 * expected.len());
-            let mut more_springs = Vec::with_capacity(4 + 5 * row.springs.len());
-
-            for i in 0..5 {
-                more_expected.extend(expected.iter().copied());
-                if i > 0 {
-                    more_springs.push(Spring::Unknown);
-                }
-                more_springs.extend(row.springs.iter().copied());
-            }
-
-            (
-                Row {
-                    springs: more_springs,
-                },
-                more_expected,
-            )
-        })
-        .map(|(row, expected)| row.count_matches(&expected))
+        .map(|line| ArrangementTable::new(&Record::from(line).unfold(5)).count())
         .sum()
 }
 
-fn parse_row(line: &str) -> (Row, Vec<usize>) {
-    let (row_str, count_str) = line.split_once(' ').unwrap();
-    let springs = row_str.chars().map(Spring::from).collect();
-    let counts = count_str.split(',').map(|n| n.parse().unwrap()).collect();
+/// Every arrangement of the springs in a single row, one per line
+pub fn arrangements(line: &str) -> String {
+    let record = Record::from(line);
+    let table = ArrangementTable::new(&record);
 
-    (Row { springs }, counts)
+    table
+        .iter()
+        .map(|springs| {
+            springs
+                .iter()
+                .map(|spring| spring.to_char())
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-#[derive(Clone, Copy, PartialEq)]
-enum Spring {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Spring {
     Working,
     Broken,
     Unknown,
 }
 
+impl Spring {
+    pub fn to_char(self) -> char {
+        match self {
+            Spring::Working => '.',
+            Spring::Broken => '#',
+            Spring::Unknown => '?',
+        }
+    }
+}
+
 impl From<char> for Spring {
     fn from(value: char) -> Self {
         match value {
@@ -64,127 +61,380 @@ impl From<char> for Spring {
     }
 }
 
-struct Row {
-    springs: Vec<Spring>,
+/// The springs of one row, some of them unknown, and the sizes of its broken groups
+#[derive(Clone)]
+pub struct Record {
+    pub springs: Vec<Spring>,
+    pub groups: Vec<usize>,
 }
 
-impl Row {
-    fn count_matches(&self, expected: &[usize]) -> usize {
-        let mut cache = HashMap::new();
-        count_matches_internal(&self.springs, expected, &mut cache)
+impl Record {
+    /// The record repeated `copies` times, with an unknown spring between each copy
+    pub fn unfold(&self, copies: usize) -> Self {
+        let mut springs = Vec::with_capacity(copies * (self.springs.len() + 1));
+        let mut groups = Vec::with_capacity(copies * self.groups.len());
+
+        for copy in 0..copies {
+            if copy > 0 {
+                springs.push(Spring::Unknown);
+            }
+            springs.extend(self.springs.iter().copied());
+            groups.extend(self.groups.iter().copied());
+        }
+
+        Self { springs, groups }
     }
 }
 
-/// count_matches_internal will always be called with the springs right _after_
-/// a possible contiguous broken block that matches a value in broken_sets, including
-/// the working (or unknown) spring as a separator
-///
-/// We start at the beginning of the remaining section and take possible offsets to match
-/// the _next_ set of spring offsets before recursing
-fn count_matches_internal(
-    springs: &[Spring],
-    broken_sets: &[usize],
-    cache: &mut HashMap<(usize, usize), usize>,
-) -> usize {
-    if let Some(cached) = cache.get(&(springs.len(), broken_sets.len())) {
-        return *cached;
+impl From<&str> for Record {
+    fn from(line: &str) -> Self {
+        let (row_str, count_str) = line.split_once(' ').unwrap();
+        let springs = row_str.chars().map(Spring::from).collect();
+        let groups = count_str.split(',').map(|n| n.parse().unwrap()).collect();
+
+        Self { springs, groups }
     }
+}
 
-    let Some((&next_broken_set, remaining_sets)) = broken_sets.split_first() else {
-        // There aren't any more broken sets expected, so we can directly determine if the
-        // springs match all working
-        let count = if springs.contains(&Spring::Broken) {
-            0
-        } else {
-            // If there aren't any broken springs, then there's only 1 possible combination
-            1
+/// Entry `(position, group)` counts the ways to fit the groups from `group` onwards into
+/// the springs from `position` onwards
+pub struct ArrangementTable {
+    springs: Vec<Spring>,
+    groups: Vec<usize>,
+    counts: Vec<usize>,
+    /// The springs from each position before the next working one, so a group fits in O(1)
+    unbroken_run: Vec<usize>,
+}
+
+impl ArrangementTable {
+    pub fn new(record: &Record) -> Self {
+        let springs = record.springs.clone();
+        let groups = record.groups.clone();
+        let len = springs.len();
+
+        let mut unbroken_run = vec![0; len + 1];
+        for position in (0..len).rev() {
+            if springs[position] != Spring::Working {
+                unbroken_run[position] = unbroken_run[position + 1] + 1;
+            }
+        }
+
+        let mut table = Self {
+            counts: vec![0; (len + 1) * (groups.len() + 1)],
+            springs,
+            groups,
+            unbroken_run,
         };
 
-        cache.insert((springs.len(), broken_sets.len()), count);
+        let finished = table.index(len, table.groups.len());
+        table.counts[finished] = 1;
 
-        return count;
-    };
+        for position in (0..len).rev() {
+            for group in 0..=table.groups.len() {
+                let mut count = 0;
 
-    // If the current broken set is longer than the remaining springs, it can't be a match
-    let max_offset = if springs.len() >= next_broken_set {
-        springs.len() - next_broken_set
-    } else {
-        cache.insert((springs.len(), broken_sets.len()), 0);
-        return 0;
-    };
+                if let Some(next) = table.working_step(position) {
+                    count += table.get(next, group);
+                }
+                if let Some(next) = table.broken_step(position, group) {
+                    count += table.get(next, group + 1);
+                }
 
-    let mut count = 0;
+                let index = table.index(position, group);
+                table.counts[index] = count;
+            }
+        }
+
+        table
+    }
 
-    for offset in 0..=max_offset {
-        // If we've skipped over any broken springs, we have gone too far and so are done trying
-        // to match
-        if springs[..offset].contains(&Spring::Broken) {
-            break;
+    fn index(&self, position: usize, group: usize) -> usize {
+        position * (self.groups.len() + 1) + group
+    }
+
+    fn get(&self, position: usize, group: usize) -> usize {
+        self.counts[self.index(position, group)]
+    }
+
+    fn working_step(&self, position: usize) -> Option<usize> {
+        (self.springs[position] != Spring::Broken).then_some(position + 1)
+    }
+
+    /// The position after a group starting at `position` and the working spring after it
+    fn broken_step(&self, position: usize, group: usize) -> Option<usize> {
+        let size = *self.groups.get(group)?;
+        let end = position + size;
+
+        if self.unbroken_run[position] < size {
+            return None;
         }
 
-        // If the current group starting at the offset contains any working springs, then it
-        // isn't a match so we can skip to the next offset
-        if springs[offset..offset + next_broken_set].contains(&Spring::Working) {
-            continue;
+        match self.springs.get(end) {
+            None => Some(end),
+            Some(Spring::Broken) => None,
+            Some(_) => Some(end + 1),
         }
+    }
 
-        // Now we have a possible match for the next broken set, so we need to recursively count
-        // the possible combinations. There are two possible cases: We are matching the last
-        // expected set of broken springs or not.
-        if remaining_sets.is_empty() {
-            // If we are matching the last set of broken springs, we don't need to worry about any
-            // separator elements, so we can skip the current set and match on the remaining.
-            count +=
-                count_matches_internal(&springs[offset + next_broken_set..], remaining_sets, cache);
+    /// Places a group at `position` (or not), returning the position and group to continue from
+    fn choose(
+        &self,
+        arrangement: &mut Vec<Spring>,
+        position: usize,
+        group: usize,
+        broken: bool,
+    ) -> Option<(usize, usize)> {
+        if broken {
+            let next = self.broken_step(position, group)?;
+            if self.get(next, group + 1) == 0 {
+                return None;
+            }
+
+            arrangement.extend(std::iter::repeat_n(Spring::Broken, self.groups[group]));
+            if next > position + self.groups[group] {
+                arrangement.push(Spring::Working);
+            }
+
+            Some((next, group + 1))
         } else {
-            // We aren't matching the _last_ set of broken springs, so we need to consider the
-            // next element _after_ the broken set to make sure it is a separator. If it doesn't
-            // exist, then we can't possibly match so we can stop checking offsets. If it is
-            // _also_ a broken spring, then we don't have a separator so this is not a match
-            match springs.get(offset + next_broken_set) {
-                None => break,
-                Some(Spring::Broken) => continue,
-                Some(_) => {
-                    count += count_matches_internal(
-                        &springs[offset + next_broken_set + 1..],
-                        remaining_sets,
-                        cache,
-                    );
+            let next = self.working_step(position)?;
+            if self.get(next, group) == 0 {
+                return None;
+            }
+
+            arrangement.push(Spring::Working);
+            Some((next, group))
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.get(0, 0)
+    }
+
+    /// Groups are placed as early as possible first, matching the puzzle text's order
+    pub fn iter(&self) -> Arrangements<'_> {
+        let stack = if self.count() > 0 {
+            vec![Frame {
+                position: 0,
+                group: 0,
+                len: 0,
+                tried: 0,
+            }]
+        } else {
+            Vec::new()
+        };
+
+        Arrangements {
+            table: self,
+            current: Vec::with_capacity(self.springs.len()),
+            stack,
+        }
+    }
+
+    /// The arrangement at `rank` in the order of `iter`, without generating the ones before it
+    pub fn nth(&self, mut rank: usize) -> Option<Vec<Spring>> {
+        if rank >= self.count() {
+            return None;
+        }
+
+        let mut arrangement = Vec::with_capacity(self.springs.len());
+        let mut position = 0;
+        let mut group = 0;
+
+        while position < self.springs.len() {
+            let broken = match self.broken_step(position, group) {
+                Some(next) if rank < self.get(next, group + 1) => true,
+                Some(next) => {
+                    rank -= self.get(next, group + 1);
+                    false
                 }
+                None => false,
+            };
+
+            (position, group) = self.choose(&mut arrangement, position, group, broken)?;
+        }
+
+        Some(arrangement)
+    }
+
+    /// `below` is given the number of arrangements and must return a random number below it
+    pub fn sample<F>(&self, below: F) -> Option<Vec<Spring>>
+    where
+        F: FnOnce(usize) -> usize,
+    {
+        match self.count() {
+            0 => None,
+            count => self.nth(below(count)),
+        }
+    }
+}
+
+struct Frame {
+    position: usize,
+    group: usize,
+    /// The arrangement's length when this frame was reached
+    len: usize,
+    tried: u8,
+}
+
+/// A depth first search over the table, following only choices with arrangements left
+pub struct Arrangements<'a> {
+    table: &'a ArrangementTable,
+    current: Vec<Spring>,
+    stack: Vec<Frame>,
+}
+
+impl Iterator for Arrangements<'_> {
+    type Item = Vec<Spring>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let frame = self.stack.last_mut()?;
+            self.current.truncate(frame.len);
+
+            if frame.position == self.table.springs.len() {
+                self.stack.pop();
+                return Some(self.current.clone());
+            }
+
+            if frame.tried == 2 {
+                self.stack.pop();
+                continue;
+            }
+
+            let broken = frame.tried == 0;
+            frame.tried += 1;
+            let (position, group) = (frame.position, frame.group);
+
+            if let Some((position, group)) =
+                self.table
+                    .choose(&mut self.current, position, group, broken)
+            {
+                self.stack.push(Frame {
+                    position,
+                    group,
+                    len: self.current.len(),
+                    tried: 0,
+                });
             }
         }
     }
+}
 
-    cache.insert((springs.len(), broken_sets.len()), count);
-    count
+/// A small seeded generator for the sampling tests
+#[cfg(test)]
+struct XorShift {
+    state: u64,
 }
 
-#[test]
-fn test_part2() {
-    assert_eq!(
-        525152,
-        solve_part2(
-            "???.### 1,1,3
+#[cfg(test)]
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // An all zero state would only ever produce zeros
+        Self { state: seed.max(1) }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number less than `bound`, rejecting the incomplete final stretch to avoid bias
+    fn below(&mut self, bound: usize) -> usize {
+        let bound = bound as u64;
+        let limit = u64::MAX - u64::MAX % bound;
+
+        loop {
+            let value = self.next_u64();
+            if value < limit {
+                return (value % bound) as usize;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "???.### 1,1,3
 .??..??...?##. 1,1,3
 ?#?#?#?#?#?#?#? 1,3,1,6
 ????.#...#... 4,1,1
 ????.######..#####. 1,6,5
-?###???????? 3,2,1"
-        )
-    );
-}
+?###???????? 3,2,1";
 
 #[test]
 fn test_part1() {
-    assert_eq!(
-        21,
-        solve_part1(
-            "???.### 1,1,3
-.??..??...?##. 1,1,3
-?#?#?#?#?#?#?#? 1,3,1,6
-????.#...#... 4,1,1
-????.######..#####. 1,6,5
-?###???????? 3,2,1"
-        )
+    assert_eq!(21, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(525152, solve_part2(EXAMPLE));
+}
+
+#[cfg(test)]
+fn groups_of(springs: &[Spring]) -> Vec<usize> {
+    springs
+        .split(|spring| *spring != Spring::Broken)
+        .map(|run| run.len())
+        .filter(|len| *len > 0)
+        .collect()
+}
+
+#[test]
+fn test_enumerate() {
+    let expected = ".###.##.#...
+.###.##..#..
+.###.##...#.
+.###.##....#
+.###..##.#..
+.###..##..#.
+.###..##...#
+.###...##.#.
+.###...##..#
+.###....##.#";
+    assert_eq!(expected, arrangements("?###???????? 3,2,1"));
+
+    for line in EXAMPLE.lines() {
+        let record = Record::from(line).unfold(2);
+        let table = ArrangementTable::new(&record);
+        let all: Vec<_> = table.iter().collect();
+
+        assert_eq!(table.count(), all.len());
+        for (rank, springs) in all.iter().enumerate() {
+            assert_eq!(record.groups, groups_of(springs));
+            assert!(springs
+                .iter()
+                .zip(&record.springs)
+                .all(|(chosen, known)| *known == Spring::Unknown || chosen == known));
+            assert_eq!(Some(springs), table.nth(rank).as_ref());
+        }
+        assert!(all.windows(2).all(|pair| pair[0] != pair[1]));
+    }
+
+    let impossible = ArrangementTable::new(&Record::from("#.# 3"));
+    assert_eq!(0, impossible.count());
+    assert_eq!(None, impossible.iter().next());
+    assert_eq!(None, impossible.sample(|_| 0));
+}
+
+#[test]
+fn test_sample() {
+    let table = ArrangementTable::new(&Record::from("?###???????? 3,2,1"));
+    let mut random = XorShift::new(12);
+    let mut seen = vec![0; table.count()];
+
+    for _ in 0..10_000 {
+        let springs = table.sample(|bound| random.below(bound)).unwrap();
+        let rank = table.iter().position(|other| other == springs).unwrap();
+        seen[rank] += 1;
+    }
+
+    // Each of the ten arrangements is expected 1000 times
+    assert!(
+        seen.iter().all(|count| (850..1150).contains(count)),
+        "{:?}",
+        seen
     );
 }
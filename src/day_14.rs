@@ -1,41 +1,59 @@
+use crate::cycle;
 use aoc_runner_derive::aoc;
-use std::collections::{hash_map::Entry, HashMap};
+
+const SPIN_CYCLES: usize = 1_000_000_000;
 
 #[aoc(day14, part1)]
 fn solve_part1(input: &str) -> usize {
-    let mut platform = Platform::from(input);
-    platform.tilt_up();
-    platform.load_up()
+    if !Bitboard::fits(input) {
+        let mut platform = Platform::from(input);
+        platform.tilt_up();
+        return platform.load_up();
+    }
+
+    let mut platform = Bitboard::from(input);
+    platform.tilt_north(0);
+    platform.load_north()
 }
 
 #[aoc(day14, part2)]
 fn solve_part2(input: &str) -> usize {
-    let mut platform = Platform::from(input);
-    let mut cache = HashMap::new();
-    cache.insert(platform.identity(), 0);
-
-    let mut cycle = 0;
-    while cycle < 1_000_000_000 {
-        platform.cycle();
-        cycle += 1;
-
-        match cache.entry(platform.identity()) {
-            Entry::Vacant(vac) => {
-                vac.insert(cycle);
-            }
-            Entry::Occupied(occ) => {
-                let previous = *occ.get();
-                let period = cycle - previous;
-                let diff = 1_000_000_000 - cycle;
-                let remaining = diff % period;
-                cycle = 1_000_000_000 - remaining;
-            }
-        }
-    }
+    load_after_cycles(input, SPIN_CYCLES)
+}
+
+#[aoc(day14, part2, Cells)]
+fn solve_part2_cells(input: &str) -> usize {
+    let platform = cycle::simulate(
+        Platform::from(input),
+        SPIN_CYCLES,
+        Platform::round_rocks,
+        Platform::cycle,
+    );
 
     platform.load_up()
 }
 
+fn load_after_cycles(input: &str, cycles: usize) -> usize {
+    if !Bitboard::fits(input) {
+        let platform = cycle::simulate(
+            Platform::from(input),
+            cycles,
+            Platform::round_rocks,
+            Platform::cycle,
+        );
+        return platform.load_up();
+    }
+
+    let platform = cycle::simulate(
+        Bitboard::from(input),
+        cycles,
+        |platform| platform.round.clone(),
+        Bitboard::cycle,
+    );
+
+    platform.load_north()
+}
+
 #[derive(Clone, Copy)]
 enum Space {
     Empty,
@@ -43,16 +61,6 @@ enum Space {
     Cube,
 }
 
-impl Space {
-    fn to_char(self) -> char {
-        match self {
-            Space::Empty => '.',
-            Space::Rounded => 'O',
-            Space::Cube => '#',
-        }
-    }
-}
-
 impl From<char> for Space {
     fn from(value: char) -> Self {
         match value {
@@ -73,23 +81,28 @@ impl Platform {
         self.map[row][col]
     }
 
-    fn identity(&self) -> String {
-        let mut result = String::with_capacity(self.map.len() * self.map[0].len());
+    /// The cube rocks never move, so the rounded rocks alone identify the state
+    fn round_rocks(&self) -> Vec<u64> {
+        let cols = self.map[0].len();
+        let mut bits = vec![0; (self.map.len() * cols).div_ceil(64)];
 
         for row in 0..self.map.len() {
-            for col in 0..self.map[0].len() {
-                result.push(self.get(row, col).to_char());
+            for col in 0..cols {
+                if matches!(self.get(row, col), Space::Rounded) {
+                    let index = row * cols + col;
+                    bits[index / 64] |= 1 << (index % 64);
+                }
             }
         }
 
-        result
+        bits
     }
 
     fn cycle(&mut self) {
-        self.tilt_up();
-        self.tilt_left();
-        self.tilt_down();
-        self.tilt_right();
+        for _ in 0..4 {
+            self.tilt_up();
+            self.rotate_clockwise();
+        }
     }
 
     fn tilt_up(&mut self) {
@@ -102,39 +115,6 @@ impl Platform {
         }
     }
 
-    fn tilt_left(&mut self) {
-        for col in 0..self.map[0].len() {
-            for row in 0..self.map.len() {
-                if matches!(self.get(row, col), Space::Rounded) {
-                    self.shift_left(row, col);
-                }
-            }
-        }
-    }
-
-    fn tilt_down(&mut self) {
-        for offset in 0..self.map.len() {
-            let row = self.map.len() - 1 - offset;
-            for col in 0..self.map[0].len() {
-                if matches!(self.get(row, col), Space::Rounded) {
-                    self.shift_down(row, col);
-                }
-            }
-        }
-    }
-
-    fn tilt_right(&mut self) {
-        let cols = self.map[0].len();
-        for offset in 0..cols {
-            let col = cols - 1 - offset;
-            for row in 0..self.map.len() {
-                if matches!(self.get(row, col), Space::Rounded) {
-                    self.shift_right(row, col);
-                }
-            }
-        }
-    }
-
     fn shift_up(&mut self, start_row: usize, start_col: usize) {
         let mut current = start_row;
 
@@ -153,83 +133,268 @@ impl Platform {
         }
     }
 
-    fn shift_left(&mut self, start_row: usize, start_col: usize) {
-        let mut current = start_col;
+    fn rotate_clockwise(&mut self) {
+        let rows = self.map.len();
 
-        while current > 0 {
-            match self.get(start_row, current - 1) {
-                Space::Cube | Space::Rounded => break,
-                Space::Empty => {
-                    current -= 1;
+        self.map = (0..self.map[0].len())
+            .map(|col| (0..rows).rev().map(|row| self.get(row, col)).collect())
+            .collect();
+    }
+
+    fn load_up(&self) -> usize {
+        let row_count = self.map.len();
+        let mut total_load = 0;
+
+        for row in 0..row_count {
+            for col in 0..self.map[0].len() {
+                if matches!(self.get(row, col), Space::Rounded) {
+                    total_load += row_count - row;
                 }
             }
         }
 
-        if current != start_col {
-            self.map[start_row][current] = Space::Rounded;
-            self.map[start_row][start_col] = Space::Empty;
+        total_load
+    }
+}
+
+impl From<&str> for Platform {
+    fn from(value: &str) -> Self {
+        let map = value
+            .lines()
+            .map(|line| line.chars().map(Space::from).collect())
+            .collect();
+
+        Self { map }
+    }
+}
+
+/// The platform as one mask per column, with bit `n` set for a rock in row `n`
+struct Bitboard {
+    rows: usize,
+    cols: usize,
+    round: Vec<u128>,
+    /// For each number of quarter turns, the runs of spaces between cube rocks in each column
+    segments: [Vec<Vec<u128>>; 4],
+}
+
+impl Bitboard {
+    fn fits(input: &str) -> bool {
+        let rows = input.lines().count();
+        let cols = input.lines().next().map_or(0, str::len);
+
+        rows <= 128 && cols <= 128
+    }
+
+    fn height(&self, rotation: usize) -> usize {
+        if rotation.is_multiple_of(2) {
+            self.rows
+        } else {
+            self.cols
         }
     }
 
-    fn shift_down(&mut self, start_row: usize, start_col: usize) {
-        let mut current = start_row;
+    fn tilt_north(&mut self, rotation: usize) {
+        for (column, segments) in self.round.iter_mut().zip(&self.segments[rotation]) {
+            let mut tilted = 0;
 
-        while current < self.map.len() - 1 {
-            match self.get(current + 1, start_col) {
-                Space::Cube | Space::Rounded => break,
-                Space::Empty => {
-                    current += 1;
-                }
+            for segment in segments {
+                let count = (*column & segment).count_ones();
+                tilted |= low_bits(count) << segment.trailing_zeros();
             }
+
+            *column = tilted;
         }
+    }
 
-        if current != start_row {
-            self.map[current][start_col] = Space::Rounded;
-            self.map[start_row][start_col] = Space::Empty;
+    fn cycle(&mut self) {
+        for rotation in 0..4 {
+            self.tilt_north(rotation);
+            self.round = rotate_clockwise(&self.round, self.height(rotation));
         }
     }
 
-    fn shift_right(&mut self, start_row: usize, start_col: usize) {
-        let mut current = start_col;
+    fn load_north(&self) -> usize {
+        self.round
+            .iter()
+            .flat_map(|column| set_bits(*column))
+            .map(|row| self.rows - row)
+            .sum()
+    }
+}
 
-        while current < self.map[0].len() - 1 {
-            match self.get(start_row, current + 1) {
-                Space::Cube | Space::Rounded => break,
-                Space::Empty => {
-                    current += 1;
+impl From<&str> for Bitboard {
+    fn from(value: &str) -> Self {
+        assert!(Self::fits(value), "Platform too large for a bitboard");
+        let rows = value.lines().count();
+        let cols = value.lines().next().unwrap().len();
+
+        let mut round = vec![0; cols];
+        let mut cubes = vec![0; cols];
+
+        for (row, line) in value.lines().enumerate() {
+            for (col, chr) in line.chars().enumerate() {
+                match Space::from(chr) {
+                    Space::Empty => {}
+                    Space::Rounded => round[col] |= 1 << row,
+                    Space::Cube => cubes[col] |= 1 << row,
                 }
             }
         }
 
-        if current != start_col {
-            self.map[start_row][current] = Space::Rounded;
-            self.map[start_row][start_col] = Space::Empty;
+        let segments = std::array::from_fn(|rotation| {
+            let height = if rotation.is_multiple_of(2) {
+                rows
+            } else {
+                cols
+            };
+            let column_segments = cubes
+                .iter()
+                .map(|column| free_segments(*column, height))
+                .collect();
+
+            cubes = rotate_clockwise(&cubes, height);
+            column_segments
+        });
+
+        Self {
+            rows,
+            cols,
+            round,
+            segments,
         }
     }
+}
 
-    fn load_up(&self) -> usize {
-        let row_count = self.map.len();
-        let mut total_load = 0;
+/// A quarter turn clockwise, so the western edge becomes the northern edge
+fn rotate_clockwise(columns: &[u128], height: usize) -> Vec<u128> {
+    let mut rotated = vec![0; height];
 
-        for row in 0..row_count {
-            for col in 0..self.map[0].len() {
-                if matches!(self.get(row, col), Space::Rounded) {
-                    total_load += row_count - row;
-                }
+    for (col, column) in columns.iter().enumerate() {
+        for row in set_bits(*column) {
+            rotated[height - 1 - row] |= 1 << col;
+        }
+    }
+
+    rotated
+}
+
+/// The maximal runs of bits below `height` that are not set in `cubes`
+fn free_segments(cubes: u128, height: usize) -> Vec<u128> {
+    let mut segments = Vec::new();
+    let mut current = 0;
+
+    for row in 0..height {
+        if cubes & (1 << row) != 0 {
+            if current != 0 {
+                segments.push(current);
+                current = 0;
             }
+        } else {
+            current |= 1 << row;
         }
+    }
 
-        total_load
+    if current != 0 {
+        segments.push(current);
     }
+
+    segments
 }
 
-impl From<&str> for Platform {
-    fn from(value: &str) -> Self {
-        let map = value
-            .lines()
-            .map(|line| line.chars().map(Space::from).collect())
-            .collect();
+fn low_bits(count: u32) -> u128 {
+    if count == 0 {
+        0
+    } else {
+        u128::MAX >> (128 - count)
+    }
+}
 
-        Self { map }
+fn set_bits(mut mask: u128) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        if mask == 0 {
+            None
+        } else {
+            let bit = mask.trailing_zeros();
+            mask &= mask - 1;
+            Some(bit as usize)
+        }
+    })
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "O....#....
+O.OO#....#
+.....##...
+OO.#O....O
+.O.....O#.
+O.#..O.#.#
+..O..#O..O
+.......O..
+#....###..
+#OO..#....";
+
+#[test]
+fn test_part1() {
+    assert_eq!(136, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(64, solve_part2(EXAMPLE));
+    // After the first three spin cycles of the puzzle text
+    assert_eq!(87, load_after_cycles(EXAMPLE, 1));
+    assert_eq!(69, load_after_cycles(EXAMPLE, 2));
+    assert_eq!(69, load_after_cycles(EXAMPLE, 3));
+}
+
+#[cfg(test)]
+fn column_masks(platform: &Platform) -> Vec<u128> {
+    let mut columns = vec![0; platform.map[0].len()];
+
+    for (row, spaces) in platform.map.iter().enumerate() {
+        for (col, space) in spaces.iter().enumerate() {
+            if matches!(space, Space::Rounded) {
+                columns[col] |= 1 << row;
+            }
+        }
+    }
+
+    columns
+}
+
+#[test]
+fn test_bitboard_matches_cells() {
+    let rectangular = "O.#O.
+.O..O
+#O.O.";
+
+    for input in [EXAMPLE, rectangular] {
+        let mut cells = Platform::from(input);
+        let mut bitboard = Bitboard::from(input);
+
+        cells.tilt_up();
+        bitboard.tilt_north(0);
+        assert_eq!(column_masks(&cells), bitboard.round);
+
+        for _ in 0..20 {
+            cells.cycle();
+            bitboard.cycle();
+
+            assert_eq!(column_masks(&cells), bitboard.round);
+            assert_eq!(cells.load_up(), bitboard.load_north());
+        }
     }
+
+    assert_eq!(solve_part2_cells(EXAMPLE), solve_part2(EXAMPLE));
+}
+
+#[test]
+fn test_large_platform() {
+    // Too wide for a bitboard, so both parts fall back to the cell-by-cell platform
+    let row = "O.#.".repeat(40);
+    let input = [row.as_str(), &".".repeat(160), &row.replace('O', ".")].join("\n");
+
+    assert!(!Bitboard::fits(&input));
+    assert_eq!(40 * 3, solve_part1(&input));
+    assert_eq!(load_after_cycles(&input, 3), solve_part2_cells(&input));
 }
@@ -10,25 +10,58 @@ fn solve_part1(input: &str) -> usize {
 #[aoc(day16, part2)]
 fn solve_part2(input: &str) -> usize {
     let grid = Grid::from(input);
-    let mut max_energized = 0;
+    let graph = SegmentGraph::from(&grid);
 
-    for row in 0..grid.rows {
-        let energized = grid.propagate_light(Point::new(row, 0), Direction::Right);
-        max_energized = max_energized.max(energized);
+    grid.edge_entries()
+        .into_iter()
+        .map(|(point, direction)| graph.energized(point, direction))
+        .max()
+        .unwrap()
+}
 
-        let energized = grid.propagate_light(Point::new(row, grid.cols - 1), Direction::Left);
-        max_energized = max_energized.max(energized);
-    }
+#[aoc(day16, part2, Bfs)]
+fn solve_part2_bfs(input: &str) -> usize {
+    let grid = Grid::from(input);
 
-    for col in 0..grid.cols {
-        let energized = grid.propagate_light(Point::new(0, col), Direction::Down);
-        max_energized = max_energized.max(energized);
+    grid.edge_entries()
+        .into_iter()
+        .map(|(point, direction)| grid.propagate_light(point, direction))
+        .max()
+        .unwrap()
+}
 
-        let energized = grid.propagate_light(Point::new(grid.rows - 1, col), Direction::Up);
-        max_energized = max_energized.max(energized);
-    }
+/// Draws the grid with every tile energized by the part 1 beam marked `#`
+pub fn render_energized(input: &str) -> String {
+    let grid = Grid::from(input);
+    let beams = grid.trace_light(Point::new(0, 0), Direction::Right);
+
+    grid.render(|point, _| if beams.contains_key(&point) { '#' } else { '.' })
+}
+
+/// Draws the part 1 beam as in the puzzle text, with arrows or direction counts
+pub fn render_beams(input: &str) -> String {
+    let grid = Grid::from(input);
+    let beams = grid.trace_light(Point::new(0, 0), Direction::Right);
 
-    max_energized
+    grid.render(|point, tile| match (tile, beams.get(&point)) {
+        (Tile::Empty, Some(directions)) if directions.len() == 1 => {
+            directions.iter().next().unwrap().arrow()
+        }
+        (Tile::Empty, Some(directions)) => char::from_digit(directions.len() as u32, 10).unwrap(),
+        _ => tile.to_char(),
+    })
+}
+
+/// Draws how many part 2 entry points energize each tile, scaled from `1` to `9`
+pub fn render_heat_map(input: &str) -> String {
+    let grid = Grid::from(input);
+    let heat = grid.heat_map();
+    let max = heat.values().copied().max().unwrap_or(0).max(1);
+
+    grid.render(|point, _| match heat.get(&point) {
+        Some(count) => char::from_digit((count * 9).div_ceil(max) as u32, 10).unwrap(),
+        None => '.',
+    })
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -39,6 +72,17 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    fn arrow(self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     row: usize,
@@ -73,6 +117,43 @@ impl From<char> for Tile {
     }
 }
 
+impl Tile {
+    fn to_char(self) -> char {
+        match self {
+            Tile::Empty => '.',
+            Tile::DownMirror => '\\',
+            Tile::UpMirror => '/',
+            Tile::UpDownSplitter => '|',
+            Tile::LeftRightSplitter => '-',
+        }
+    }
+
+    /// The beams leaving this tile when one enters moving in `direction`
+    fn beams(self, direction: Direction) -> [Option<Direction>; 2] {
+        match (self, direction) {
+            (Tile::Empty, _) => [Some(direction), None],
+            (Tile::DownMirror, Direction::Right) => [Some(Direction::Down), None],
+            (Tile::DownMirror, Direction::Left) => [Some(Direction::Up), None],
+            (Tile::DownMirror, Direction::Up) => [Some(Direction::Left), None],
+            (Tile::DownMirror, Direction::Down) => [Some(Direction::Right), None],
+            (Tile::UpMirror, Direction::Right) => [Some(Direction::Up), None],
+            (Tile::UpMirror, Direction::Left) => [Some(Direction::Down), None],
+            (Tile::UpMirror, Direction::Up) => [Some(Direction::Right), None],
+            (Tile::UpMirror, Direction::Down) => [Some(Direction::Left), None],
+            (Tile::UpDownSplitter, Direction::Down | Direction::Up) => [Some(direction), None],
+            (Tile::UpDownSplitter, Direction::Left | Direction::Right) => {
+                [Some(Direction::Up), Some(Direction::Down)]
+            }
+            (Tile::LeftRightSplitter, Direction::Left | Direction::Right) => {
+                [Some(direction), None]
+            }
+            (Tile::LeftRightSplitter, Direction::Up | Direction::Down) => {
+                [Some(Direction::Left), Some(Direction::Right)]
+            }
+        }
+    }
+}
+
 struct Grid {
     tiles: HashMap<Point, Tile>,
     rows: usize,
@@ -94,54 +175,277 @@ impl Grid {
     }
 
     fn propagate_light(&self, start: Point, dir: Direction) -> usize {
+        self.trace_light(start, dir).len()
+    }
+
+    fn trace_light(&self, start: Point, dir: Direction) -> HashMap<Point, HashSet<Direction>> {
         let mut visited: HashMap<Point, HashSet<Direction>> = HashMap::new();
         let mut queue = VecDeque::new();
         queue.push_back((start, dir));
 
-        macro_rules! maybe_queue {
-            ($point:expr, $dir:expr) => {
-                if let Some(point) = self.shift($point, $dir) {
-                    let seen = visited.get(&point).is_some_and(|dirs| dirs.contains(&$dir));
+        while let Some((point, direction)) = queue.pop_front() {
+            visited.entry(point).or_default().insert(direction);
+            let tile = self.tiles.get(&point).unwrap();
+
+            for next_dir in tile.beams(direction).into_iter().flatten() {
+                if let Some(next) = self.shift(point, next_dir) {
+                    let seen = visited
+                        .get(&next)
+                        .is_some_and(|dirs| dirs.contains(&next_dir));
 
                     if !seen {
-                        queue.push_back((point, $dir));
+                        queue.push_back((next, next_dir));
                     }
                 }
+            }
+        }
+
+        visited
+    }
+
+    /// The number of edge entry points from which a beam energizes each tile
+    fn heat_map(&self) -> HashMap<Point, usize> {
+        let mut heat = HashMap::new();
+
+        for (point, direction) in self.edge_entries() {
+            for energized in self.trace_light(point, direction).into_keys() {
+                *heat.entry(energized).or_default() += 1;
+            }
+        }
+
+        heat
+    }
+
+    fn render<F>(&self, mut draw: F) -> String
+    where
+        F: FnMut(Point, Tile) -> char,
+    {
+        (0..self.rows)
+            .map(|row| {
+                (0..self.cols)
+                    .map(|col| {
+                        let point = Point::new(row, col);
+                        draw(point, *self.tiles.get(&point).unwrap())
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn edge_entries(&self) -> Vec<(Point, Direction)> {
+        let mut entries = Vec::new();
+
+        for row in 0..self.rows {
+            entries.push((Point::new(row, 0), Direction::Right));
+            entries.push((Point::new(row, self.cols - 1), Direction::Left));
+        }
+
+        for col in 0..self.cols {
+            entries.push((Point::new(0, col), Direction::Down));
+            entries.push((Point::new(self.rows - 1, col), Direction::Up));
+        }
+
+        entries
+    }
+}
+
+/// Beam paths split into straight segments. Segments in a loop energize the same tiles, so
+/// each strongly connected component's tiles are found once.
+struct SegmentGraph {
+    segments: HashMap<(Point, Direction), usize>,
+    components: Vec<usize>,
+    energized: Vec<usize>,
+}
+
+impl SegmentGraph {
+    fn energized(&self, start: Point, direction: Direction) -> usize {
+        let segment = self.segments[&(start, direction)];
+
+        self.energized[self.components[segment]]
+    }
+}
+
+impl From<&Grid> for SegmentGraph {
+    fn from(grid: &Grid) -> Self {
+        let mut segments = HashMap::new();
+        let mut tiles: Vec<Vec<usize>> = Vec::new();
+        let mut next: Vec<Vec<(Point, Direction)>> = Vec::new();
+        let mut queue: VecDeque<_> = grid.edge_entries().into();
+
+        while let Some(start) = queue.pop_front() {
+            if segments.contains_key(&start) {
+                continue;
+            }
+
+            segments.insert(start, tiles.len());
+
+            let (mut point, direction) = start;
+            let mut covered = Vec::new();
+
+            let beams = loop {
+                covered.push(point.row * grid.cols + point.col);
+                let beams = grid.tiles.get(&point).unwrap().beams(direction);
+
+                if beams != [Some(direction), None] {
+                    break beams;
+                }
+
+                match grid.shift(point, direction) {
+                    Some(shifted) => point = shifted,
+                    None => break [None, None],
+                }
             };
+
+            let successors: Vec<_> = beams
+                .into_iter()
+                .flatten()
+                .filter_map(|dir| grid.shift(point, dir).map(|shifted| (shifted, dir)))
+                .collect();
+
+            queue.extend(successors.iter().copied());
+            tiles.push(covered);
+            next.push(successors);
         }
 
-        while let Some((point, direction)) = queue.pop_front() {
-            visited.entry(point).or_default().insert(direction);
-            let tile = self.tiles.get(&point).unwrap();
+        let next: Vec<Vec<usize>> = next
+            .iter()
+            .map(|successors| successors.iter().map(|state| segments[state]).collect())
+            .collect();
+
+        let mut tarjan = Tarjan::new(&next);
+        for segment in 0..next.len() {
+            tarjan.visit(segment);
+        }
+
+        // Tarjan's algorithm finishes every component after all of the components it leads to,
+        // so each set only has to be combined with sets that are already complete
+        let mut components = vec![0; next.len()];
+        let mut sets: Vec<BitSet> = Vec::new();
 
-            match (tile, direction) {
-                (Tile::Empty, _) => maybe_queue!(point, direction),
-                (Tile::DownMirror, Direction::Right) => maybe_queue!(point, Direction::Down),
-                (Tile::DownMirror, Direction::Left) => maybe_queue!(point, Direction::Up),
-                (Tile::DownMirror, Direction::Up) => maybe_queue!(point, Direction::Left),
-                (Tile::DownMirror, Direction::Down) => maybe_queue!(point, Direction::Right),
-                (Tile::UpMirror, Direction::Right) => maybe_queue!(point, Direction::Up),
-                (Tile::UpMirror, Direction::Left) => maybe_queue!(point, Direction::Down),
-                (Tile::UpMirror, Direction::Up) => maybe_queue!(point, Direction::Right),
-                (Tile::UpMirror, Direction::Down) => maybe_queue!(point, Direction::Left),
-                (Tile::UpDownSplitter, Direction::Down) => maybe_queue!(point, Direction::Down),
-                (Tile::UpDownSplitter, Direction::Up) => maybe_queue!(point, Direction::Up),
-                (Tile::UpDownSplitter, Direction::Left | Direction::Right) => {
-                    maybe_queue!(point, Direction::Up);
-                    maybe_queue!(point, Direction::Down);
+        for (component, members) in tarjan.components.iter().enumerate() {
+            let mut set = BitSet::new(grid.rows * grid.cols);
+
+            for &segment in members {
+                components[segment] = component;
+                for &tile in &tiles[segment] {
+                    set.insert(tile);
                 }
-                (Tile::LeftRightSplitter, Direction::Left) => maybe_queue!(point, Direction::Left),
-                (Tile::LeftRightSplitter, Direction::Right) => {
-                    maybe_queue!(point, Direction::Right)
+            }
+
+            for &segment in members {
+                for &successor in &next[segment] {
+                    if components[successor] != component {
+                        set.union_with(&sets[components[successor]]);
+                    }
                 }
-                (Tile::LeftRightSplitter, Direction::Up | Direction::Down) => {
-                    maybe_queue!(point, Direction::Left);
-                    maybe_queue!(point, Direction::Right);
+            }
+
+            sets.push(set);
+        }
+
+        Self {
+            segments,
+            components,
+            energized: sets.iter().map(BitSet::len).collect(),
+        }
+    }
+}
+
+/// Tarjan's strongly connected components algorithm
+struct Tarjan<'a> {
+    edges: &'a [Vec<usize>],
+    index: Vec<Option<usize>>,
+    lowlink: Vec<usize>,
+    on_stack: Vec<bool>,
+    stack: Vec<usize>,
+    counter: usize,
+    components: Vec<Vec<usize>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn new(edges: &'a [Vec<usize>]) -> Self {
+        Self {
+            edges,
+            index: vec![None; edges.len()],
+            lowlink: vec![0; edges.len()],
+            on_stack: vec![false; edges.len()],
+            stack: Vec::new(),
+            counter: 0,
+            components: Vec::new(),
+        }
+    }
+
+    fn visit(&mut self, node: usize) {
+        if self.index[node].is_some() {
+            return;
+        }
+
+        self.index[node] = Some(self.counter);
+        self.lowlink[node] = self.counter;
+        self.counter += 1;
+        self.stack.push(node);
+        self.on_stack[node] = true;
+
+        for i in 0..self.edges[node].len() {
+            let successor = self.edges[node][i];
+
+            match self.index[successor] {
+                None => {
+                    self.visit(successor);
+                    self.lowlink[node] = self.lowlink[node].min(self.lowlink[successor]);
+                }
+                Some(index) if self.on_stack[successor] => {
+                    self.lowlink[node] = self.lowlink[node].min(index);
                 }
+                Some(_) => {}
             }
         }
 
-        visited.len()
+        if Some(self.lowlink[node]) == self.index[node] {
+            let mut component = Vec::new();
+
+            loop {
+                let member = self.stack.pop().unwrap();
+                self.on_stack[member] = false;
+                component.push(member);
+
+                if member == node {
+                    break;
+                }
+            }
+
+            self.components.push(component);
+        }
+    }
+}
+
+struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    fn new(size: usize) -> Self {
+        Self {
+            words: vec![0; size.div_ceil(64)],
+        }
+    }
+
+    fn insert(&mut self, index: usize) {
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    fn union_with(&mut self, other: &Self) {
+        for (word, other_word) in self.words.iter_mut().zip(&other.words) {
+            *word |= other_word;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
     }
 }
 
@@ -163,9 +467,8 @@ impl From<&str> for Grid {
     }
 }
 
-#[test]
-fn test_part1() {
-    let input = r#".|...\....
+#[cfg(test)]
+const EXAMPLE: &str = r#".|...\....
 |.-.\.....
 .....|-...
 ........|.
@@ -176,5 +479,67 @@ fn test_part1() {
 .|....-|.\
 ..//.|...."#;
 
-    assert_eq!(46, solve_part1(input));
+#[test]
+fn test_part1() {
+    assert_eq!(46, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(51, solve_part2(EXAMPLE));
+    assert_eq!(51, solve_part2_bfs(EXAMPLE));
+}
+
+#[test]
+fn test_segment_graph_matches_bfs() {
+    let looping = r#"..\..../
+.|..-...
+........
+..\.-../
+../....\"#;
+
+    for input in [EXAMPLE, looping] {
+        let grid = Grid::from(input);
+        let graph = SegmentGraph::from(&grid);
+
+        for (point, direction) in grid.edge_entries() {
+            assert_eq!(
+                grid.propagate_light(point, direction),
+                graph.energized(point, direction)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_render() {
+    let energized = "######....
+.#...#....
+.#...#####
+.#...##...
+.#...##...
+.#...##...
+.#..####..
+########..
+.#######..
+.#...#.#..";
+    assert_eq!(energized, render_energized(EXAMPLE));
+
+    let beams = r#">|<<<\....
+|v-.\^....
+.v...|->>>
+.v...v^.|.
+.v...v^...
+.v...v^..\
+.v../2\\..
+<->-/vv|..
+.|<<<2-|.\
+.v//.|.v.."#;
+    assert_eq!(beams, render_beams(EXAMPLE));
+
+    let heat = render_heat_map(EXAMPLE);
+    assert_eq!(EXAMPLE.lines().count(), heat.lines().count());
+    // Every tile in the example is energized from at least one entry point
+    assert!(!heat.contains('.'));
+    assert!(heat.contains('9'));
 }
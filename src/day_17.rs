@@ -1,43 +1,55 @@
+use crate::dial::Dial;
 use crate::dijkstra::{Dijkstra, Value};
 use aoc_runner_derive::aoc;
-use std::collections::HashMap;
 
 #[aoc(day17, part1)]
 fn solve_part1(input: &str) -> usize {
     let grid = Grid::from(input);
-
-    let (_, cost) = Dijkstra::new(
-        Position {
-            point: Point::new(0, 0),
-            direction: Direction::Left,
-            count: 0,
-        },
-        |position| grid.destination(position.point),
-        |position| grid.neighbors_part1(*position),
-    )
-    .next()
-    .unwrap();
-
-    cost
+    grid.minimum_heat_loss(&CrucibleRules::CRUCIBLE)
 }
 
 #[aoc(day17, part2)]
 fn solve_part2(input: &str) -> usize {
     let grid = Grid::from(input);
+    grid.minimum_heat_loss(&CrucibleRules::ULTRA_CRUCIBLE)
+}
 
-    let (_, cost) = Dijkstra::new(
-        Position {
-            point: Point::new(0, 0),
-            direction: Direction::Left,
-            count: 0,
-        },
-        |position| grid.destination(position.point),
-        |position| grid.neighbors_part2(*position),
-    )
-    .next()
-    .unwrap();
+#[aoc(day17, part1, Hashed)]
+fn solve_part1_hashed(input: &str) -> usize {
+    let grid = Grid::from(input);
+    grid.minimum_heat_loss_hashed(&CrucibleRules::CRUCIBLE)
+}
 
-    cost
+#[aoc(day17, part2, Hashed)]
+fn solve_part2_hashed(input: &str) -> usize {
+    let grid = Grid::from(input);
+    grid.minimum_heat_loss_hashed(&CrucibleRules::ULTRA_CRUCIBLE)
+}
+
+struct CrucibleRules {
+    /// Blocks in a straight line before the crucible can turn or stop
+    min_straight: u8,
+    /// Blocks in a straight line before the crucible must turn
+    max_straight: u8,
+    can_reverse: bool,
+    /// Additional heat lost every time the crucible changes direction
+    turn_cost: usize,
+}
+
+impl CrucibleRules {
+    const CRUCIBLE: Self = Self {
+        min_straight: 1,
+        max_straight: 3,
+        can_reverse: false,
+        turn_cost: 0,
+    };
+
+    const ULTRA_CRUCIBLE: Self = Self {
+        min_straight: 4,
+        max_straight: 10,
+        can_reverse: false,
+        turn_cost: 0,
+    };
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -48,6 +60,24 @@ enum Direction {
     Right,
 }
 
+impl Direction {
+    const ALL: [Direction; 4] = [
+        Direction::Up,
+        Direction::Down,
+        Direction::Left,
+        Direction::Right,
+    ];
+
+    fn reverse(self) -> Self {
+        match self {
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     row: usize,
@@ -78,7 +108,7 @@ impl Value for Position {
 struct Grid {
     rows: usize,
     cols: usize,
-    costs: HashMap<Point, usize>,
+    costs: Vec<u8>,
 }
 
 impl Grid {
@@ -86,111 +116,81 @@ impl Grid {
         point.row == self.rows - 1 && point.col == self.cols - 1
     }
 
-    fn neighbors_part1(&self, position: Position) -> Vec<(Position, usize)> {
-        let mut options = Vec::with_capacity(3);
+    fn cost(&self, point: Point) -> usize {
+        self.costs[point.row * self.cols + point.col] as usize
+    }
 
-        match (position.direction, position.count) {
-            (Direction::Up, count @ 1..=2) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Up, count + 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Right, 1));
-            }
-            (Direction::Up, 3) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Right, 1));
-            }
-            (Direction::Down, count @ 0..=2) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Down, count + 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Right, 1));
-            }
-            (Direction::Down, 3) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Right, 1));
-            }
-            (Direction::Left, count @ 0..=2) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Left, count + 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Down, 1));
-            }
-            (Direction::Left, 3) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Down, 1));
-            }
-            (Direction::Right, count @ 0..=2) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Right, count + 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Down, 1));
-            }
-            (Direction::Right, 3) => {
-                options.extend(self.generate_neighbor(position.point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(position.point, Direction::Down, 1));
-            }
-            _ => unreachable!(),
+    fn start() -> Position {
+        // A count of 0 marks the crucible as not having started moving yet
+        Position {
+            point: Point::new(0, 0),
+            direction: Direction::Right,
+            count: 0,
         }
+    }
 
-        options
+    fn index(&self, position: Position, rules: &CrucibleRules) -> usize {
+        let tile = position.point.row * self.cols + position.point.col;
+        let direction = position.direction as usize;
+        let counts = rules.max_straight as usize + 1;
+
+        (tile * 4 + direction) * counts + position.count as usize
     }
 
-    fn neighbors_part2(&self, position: Position) -> Vec<(Position, usize)> {
-        let mut options = Vec::with_capacity(3);
+    fn minimum_heat_loss(&self, rules: &CrucibleRules) -> usize {
+        let states = self.rows * self.cols * 4 * (rules.max_straight as usize + 1);
+
+        let (_, cost) = Dial::new(
+            Self::start(),
+            9 + rules.turn_cost,
+            states,
+            |position| self.index(*position, rules),
+            |position| self.destination(position.point) && position.count >= rules.min_straight,
+            |position| self.neighbors(*position, rules),
+        )
+        .next()
+        .unwrap();
+
+        cost
+    }
+
+    fn minimum_heat_loss_hashed(&self, rules: &CrucibleRules) -> usize {
+        let (_, cost) = Dijkstra::new(
+            Self::start(),
+            |position| self.destination(position.point) && position.count >= rules.min_straight,
+            |position| self.neighbors(*position, rules),
+        )
+        .next()
+        .unwrap();
+
+        cost
+    }
+
+    fn neighbors(&self, position: Position, rules: &CrucibleRules) -> Vec<(Position, usize)> {
+        let mut options = Vec::with_capacity(4);
         let point = position.point;
-        match (position.direction, position.count) {
-            (_, 0) => {
-                options.extend(self.generate_neighbor(point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(point, Direction::Down, 1));
-                options.extend(self.generate_neighbor(point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(point, Direction::Right, 1));
-            }
-            (Direction::Up, count @ 1..=3) => {
-                options.extend(self.generate_neighbor(point, Direction::Up, count + 1));
-            }
-            (Direction::Up, count @ 4..=9) => {
-                options.extend(self.generate_neighbor(point, Direction::Up, count + 1));
-                options.extend(self.generate_neighbor(point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(point, Direction::Right, 1));
-            }
-            (Direction::Up, 10) => {
-                options.extend(self.generate_neighbor(point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(point, Direction::Right, 1));
-            }
-            (Direction::Down, count @ 1..=3) => {
-                options.extend(self.generate_neighbor(point, Direction::Down, count + 1));
-            }
-            (Direction::Down, count @ 4..=9) => {
-                options.extend(self.generate_neighbor(point, Direction::Down, count + 1));
-                options.extend(self.generate_neighbor(point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(point, Direction::Right, 1));
-            }
-            (Direction::Down, 10) => {
-                options.extend(self.generate_neighbor(point, Direction::Left, 1));
-                options.extend(self.generate_neighbor(point, Direction::Right, 1));
-            }
-            (Direction::Left, count @ 1..=3) => {
-                options.extend(self.generate_neighbor(point, Direction::Left, count + 1));
-            }
-            (Direction::Left, count @ 4..=9) => {
-                options.extend(self.generate_neighbor(point, Direction::Left, count + 1));
-                options.extend(self.generate_neighbor(point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(point, Direction::Down, 1));
-            }
-            (Direction::Left, 10) => {
-                options.extend(self.generate_neighbor(point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(point, Direction::Down, 1));
-            }
-            (Direction::Right, count @ 1..=3) => {
-                options.extend(self.generate_neighbor(point, Direction::Right, count + 1));
-            }
-            (Direction::Right, count @ 4..=9) => {
-                options.extend(self.generate_neighbor(point, Direction::Right, count + 1));
-                options.extend(self.generate_neighbor(point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(point, Direction::Down, 1));
+
+        if position.count == 0 {
+            for direction in Direction::ALL {
+                options.extend(self.generate_neighbor(point, direction, 1));
             }
-            (Direction::Right, 10) => {
-                options.extend(self.generate_neighbor(point, Direction::Up, 1));
-                options.extend(self.generate_neighbor(point, Direction::Down, 1));
+
+            return options;
+        }
+
+        for direction in Direction::ALL {
+            if direction == position.direction {
+                if position.count < rules.max_straight {
+                    options.extend(self.generate_neighbor(point, direction, position.count + 1));
+                }
+            } else if position.count >= rules.min_straight
+                && (rules.can_reverse || direction != position.direction.reverse())
+            {
+                options.extend(
+                    self.generate_neighbor(point, direction, 1)
+                        .map(|(next, cost)| (next, cost + rules.turn_cost)),
+                );
             }
-            _ => unreachable!(),
         }
 
         options
@@ -203,7 +203,7 @@ impl Grid {
         count: u8,
     ) -> Option<(Position, usize)> {
         if let Some(point) = self.shift(point, direction) {
-            let cost = *self.costs.get(&point).unwrap();
+            let cost = self.cost(point);
             Some((
                 Position {
                     point,
@@ -233,16 +233,13 @@ impl Grid {
 
 impl From<&str> for Grid {
     fn from(value: &str) -> Self {
-        let mut costs = HashMap::new();
+        let mut costs = Vec::new();
         let mut rows = 0;
         let mut cols = 0;
 
         for (row, line) in value.lines().enumerate() {
             for (col, cost_chr) in line.chars().enumerate() {
-                costs.insert(
-                    Point::new(row, col),
-                    cost_chr.to_digit(10).unwrap() as usize,
-                );
+                costs.push(cost_chr.to_digit(10).unwrap() as u8);
                 cols = cols.max(col + 1);
             }
             rows = rows.max(row + 1);
@@ -251,3 +248,82 @@ impl From<&str> for Grid {
         Self { rows, cols, costs }
     }
 }
+
+#[cfg(test)]
+const EXAMPLE: &str = "2413432311323
+3215453535623
+3255245654254
+3446585845452
+4546657867536
+1438598798454
+4457876987766
+3637877979653
+4654967986887
+4564679986453
+1224686865563
+2546548887735
+4322674655533";
+
+#[test]
+fn test_part1() {
+    assert_eq!(102, solve_part1(EXAMPLE));
+    assert_eq!(102, solve_part1_hashed(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(94, solve_part2(EXAMPLE));
+    assert_eq!(94, solve_part2_hashed(EXAMPLE));
+
+    let input = "111111111111
+999999999991
+999999999991
+999999999991
+999999999991";
+    assert_eq!(71, solve_part2(input));
+    assert_eq!(71, solve_part2_hashed(input));
+}
+
+#[test]
+fn test_custom_rules() {
+    let grid = Grid::from("123\n456");
+    let free = CrucibleRules {
+        min_straight: 1,
+        max_straight: 10,
+        can_reverse: false,
+        turn_cost: 0,
+    };
+    assert_eq!(11, grid.minimum_heat_loss(&free));
+
+    let expensive_turns = CrucibleRules {
+        turn_cost: 5,
+        ..free
+    };
+    assert_eq!(16, grid.minimum_heat_loss(&expensive_turns));
+
+    let zig_zag = CrucibleRules {
+        max_straight: 1,
+        ..free
+    };
+    assert_eq!(13, grid.minimum_heat_loss(&zig_zag));
+
+    for rules in [&free, &expensive_turns, &zig_zag] {
+        assert_eq!(
+            grid.minimum_heat_loss(rules),
+            grid.minimum_heat_loss_hashed(rules)
+        );
+    }
+
+    let grid = Grid::from("111\n111\n111");
+    let moving = Position {
+        point: Point::new(1, 1),
+        direction: Direction::Right,
+        count: 1,
+    };
+    let reversing = CrucibleRules {
+        can_reverse: true,
+        ..free
+    };
+    assert_eq!(3, grid.neighbors(moving, &free).len());
+    assert_eq!(4, grid.neighbors(moving, &reversing).len());
+}
@@ -1,19 +1,99 @@
+use crate::geometry::Polygon;
 use aoc_runner_derive::aoc;
+use std::fmt;
 
 #[aoc(day18, part1)]
-fn solve_part1(input: &str) -> isize {
+fn solve_part1(input: &str) -> i128 {
     let mut plot = Plot::default();
     plot.dig(input.lines().map(DigInstruction::from_part1));
+    plot.validate().unwrap_or_else(|err| panic!("{}", err));
     plot.count_space()
 }
 
 #[aoc(day18, part2)]
-fn solve_part2(input: &str) -> isize {
+fn solve_part2(input: &str) -> i128 {
     let mut plot = Plot::default();
     plot.dig(input.lines().map(DigInstruction::from_part2));
+    plot.validate().unwrap_or_else(|err| panic!("{}", err));
     plot.count_space()
 }
 
+/// Checks that the part 1 dig plan forms a single closed loop that never crosses itself
+pub fn validate_plan(input: &str) -> Result<(), DigError> {
+    let mut plot = Plot::default();
+    plot.dig(input.lines().map(DigInstruction::from_part1));
+    plot.validate()
+}
+
+/// Draws the trench from the part 1 dig plan, with `#` for trench and `.` for level ground
+pub fn render_ascii(input: &str) -> String {
+    let mut plot = Plot::default();
+    plot.dig(input.lines().map(DigInstruction::from_part1));
+
+    plot.render()
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|cell| if cell.is_some() { '#' } else { '.' })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Draws the part 1 trench as a plain-text PPM image, coloured by instruction
+pub fn render_ppm(input: &str) -> String {
+    let mut plot = Plot::default();
+    plot.dig(input.lines().map(DigInstruction::from_part1));
+    let cells = plot.render();
+
+    let mut image = format!("P3\n{} {}\n255\n", cells[0].len(), cells.len());
+    for row in cells {
+        let pixels: Vec<_> = row
+            .into_iter()
+            .map(|cell| {
+                let colour = cell.unwrap_or(0xffffff);
+                format!(
+                    "{} {} {}",
+                    (colour >> 16) & 0xff,
+                    (colour >> 8) & 0xff,
+                    colour & 0xff
+                )
+            })
+            .collect();
+
+        image.push_str(&pixels.join(" "));
+        image.push('\n');
+    }
+
+    image
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DigError {
+    /// The trench doesn't return to where it started
+    Unclosed { row: isize, col: isize },
+    /// Two instructions dig through the same ground, other than neighbours meeting at a corner
+    SelfIntersection { first: usize, second: usize },
+}
+
+impl fmt::Display for DigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigError::Unclosed { row, col } => write!(
+                f,
+                "trench ends at ({}, {}) instead of returning to the start",
+                row, col
+            ),
+            DigError::SelfIntersection { first, second } => write!(
+                f,
+                "instruction {} crosses the trench dug by instruction {}",
+                second, first
+            ),
+        }
+    }
+}
+
 #[derive(Clone, Copy)]
 enum Direction {
     Up,
@@ -37,6 +117,7 @@ impl From<&str> for Direction {
 struct DigInstruction {
     direction: Direction,
     distance: isize,
+    colour: Option<u32>,
 }
 
 impl DigInstruction {
@@ -44,10 +125,13 @@ impl DigInstruction {
         let mut parts = value.split(' ');
         let direction = parts.next().unwrap().into();
         let distance = parts.next().unwrap().parse().unwrap();
+        let hex = parts.next().unwrap();
+        let colour = u32::from_str_radix(&hex[2..8], 16).unwrap();
 
         Self {
             direction,
             distance,
+            colour: Some(colour),
         }
     }
 
@@ -59,6 +143,7 @@ impl DigInstruction {
         Self {
             direction,
             distance,
+            colour: None,
         }
     }
 }
@@ -87,7 +172,7 @@ impl Point {
 #[derive(Default)]
 struct Plot {
     vertices: Vec<Point>,
-    perimeter: isize,
+    colours: Vec<Option<u32>>,
 }
 
 impl Plot {
@@ -101,20 +186,216 @@ impl Plot {
         for instruction in instructions {
             current = current.shift(instruction.direction, instruction.distance);
             self.vertices.push(current);
-            self.perimeter += instruction.distance;
+            self.colours.push(instruction.colour);
+        }
+    }
+
+    fn edge(&self, index: usize) -> (Point, Point) {
+        (self.vertices[index], self.vertices[index + 1])
+    }
+
+    fn validate(&self) -> Result<(), DigError> {
+        let start = self.vertices[0];
+        let end = self.vertices[self.vertices.len() - 1];
+
+        if start != end {
+            return Err(DigError::Unclosed {
+                row: end.row,
+                col: end.col,
+            });
+        }
+
+        let edges = self.colours.len();
+
+        for second in 1..edges {
+            for first in 0..second {
+                // Neighbouring instructions always share a corner, including the first and last
+                // which meet back at the start
+                let neighbours = second == first + 1 || (first == 0 && second == edges - 1);
+                let overlap = overlap_size(self.edge(first), self.edge(second));
+
+                let crossing = match overlap {
+                    None => false,
+                    Some(size) if neighbours => size > 1,
+                    Some(_) => true,
+                };
+
+                if crossing {
+                    return Err(DigError::SelfIntersection { first, second });
+                }
+            }
         }
+
+        Ok(())
     }
 
-    fn count_space(&self) -> isize {
-        let mut pair_sums = 0;
+    /// The colour of every cube in the bounding box of the trench, if it was dug
+    fn render(&self) -> Vec<Vec<Option<u32>>> {
+        let min_row = self.vertices.iter().map(|p| p.row).min().unwrap();
+        let max_row = self.vertices.iter().map(|p| p.row).max().unwrap();
+        let min_col = self.vertices.iter().map(|p| p.col).min().unwrap();
+        let max_col = self.vertices.iter().map(|p| p.col).max().unwrap();
 
-        for i in 1..self.vertices.len() {
-            let first = self.vertices[i - 1];
-            let second = self.vertices[i];
+        let rows = (max_row - min_row + 1) as usize;
+        let cols = (max_col - min_col + 1) as usize;
+        let mut cells = vec![vec![None; cols]; rows];
 
-            pair_sums += first.row * second.col - first.col * second.row;
+        for (index, colour) in self.colours.iter().enumerate() {
+            let (start, end) = self.edge(index);
+
+            for row in start.row.min(end.row)..=start.row.max(end.row) {
+                for col in start.col.min(end.col)..=start.col.max(end.col) {
+                    cells[(row - min_row) as usize][(col - min_col) as usize] =
+                        Some(colour.unwrap_or(0));
+                }
+            }
         }
 
-        ((pair_sums.abs() + self.perimeter) / 2) + 1
+        cells
+    }
+
+    fn count_space(&self) -> i128 {
+        let polygon = Polygon::new(
+            self.vertices
+                .iter()
+                .map(|point| (point.row as i128, point.col as i128)),
+        );
+
+        // The trench is one cube wide, so every lattice point on or inside the loop is dug out
+        polygon.enclosed_points().unwrap()
     }
 }
+
+fn overlap_size(first: (Point, Point), second: (Point, Point)) -> Option<isize> {
+    let row_start = first
+        .0
+        .row
+        .min(first.1.row)
+        .max(second.0.row.min(second.1.row));
+    let row_end = first
+        .0
+        .row
+        .max(first.1.row)
+        .min(second.0.row.max(second.1.row));
+    let col_start = first
+        .0
+        .col
+        .min(first.1.col)
+        .max(second.0.col.min(second.1.col));
+    let col_end = first
+        .0
+        .col
+        .max(first.1.col)
+        .min(second.0.col.max(second.1.col));
+
+    if row_start > row_end || col_start > col_end {
+        None
+    } else {
+        Some((row_end - row_start + 1) * (col_end - col_start + 1))
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "R 6 (#70c710)
+D 5 (#0dc571)
+L 2 (#5713f0)
+D 2 (#d2c081)
+R 2 (#59c680)
+D 2 (#411b91)
+L 5 (#8ceee2)
+U 2 (#caa173)
+L 1 (#1b58a2)
+U 2 (#caa171)
+R 2 (#7807d2)
+U 3 (#a77fa3)
+L 2 (#015232)
+U 2 (#7a21e3)";
+
+#[test]
+fn test_part1() {
+    assert_eq!(62, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(952408144115, solve_part2(EXAMPLE));
+}
+
+#[test]
+fn test_unit_steps_match_corners() {
+    // Digging one cube at a time visits every boundary point as a vertex, so the count must
+    // agree with the polygon built from the corners alone
+    let mut plot = Plot::default();
+    plot.dig(EXAMPLE.lines().flat_map(|line| {
+        let instruction = DigInstruction::from_part1(line);
+
+        (0..instruction.distance).map(move |_| DigInstruction {
+            direction: instruction.direction,
+            distance: 1,
+            colour: instruction.colour,
+        })
+    }));
+
+    assert_eq!(solve_part1(EXAMPLE), plot.count_space());
+}
+
+#[test]
+fn test_validate() {
+    assert_eq!(Ok(()), validate_plan(EXAMPLE));
+
+    assert_eq!(
+        Err(DigError::Unclosed { row: 2, col: 3 }),
+        validate_plan("R 3 (#000000)\nD 2 (#000000)")
+    );
+
+    let crossing = "R 4 (#000000)
+D 2 (#000000)
+L 2 (#000000)
+U 4 (#000000)
+L 2 (#000000)
+D 2 (#000000)";
+    assert_eq!(
+        Err(DigError::SelfIntersection {
+            first: 0,
+            second: 3
+        }),
+        validate_plan(crossing)
+    );
+
+    let doubling_back = "R 4 (#000000)
+L 1 (#000000)
+D 2 (#000000)
+L 3 (#000000)
+U 2 (#000000)";
+    assert_eq!(
+        Err(DigError::SelfIntersection {
+            first: 0,
+            second: 1
+        }),
+        validate_plan(doubling_back)
+    );
+}
+
+#[test]
+fn test_render() {
+    let expected = "#######
+#.....#
+###...#
+..#...#
+..#...#
+###.###
+#...#..
+##..###
+.#....#
+.######";
+
+    assert_eq!(expected, render_ascii(EXAMPLE));
+
+    let ppm = render_ppm(EXAMPLE);
+    let mut lines = ppm.lines();
+    assert_eq!(Some("P3"), lines.next());
+    assert_eq!(Some("7 10"), lines.next());
+    assert_eq!(Some("255"), lines.next());
+    // The top-left corner is dug by the final instruction, #7a21e3
+    assert!(lines.next().unwrap().starts_with("122 33 227 112 199 16"));
+}
@@ -1,7 +1,10 @@
 use aoc_runner_derive::aoc;
-use std::collections::{HashMap, VecDeque};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
 use std::ops::RangeInclusive;
 
+const RATING_BOUNDS: RangeInclusive<usize> = 1..=4000;
+
 #[aoc(day19, part1)]
 fn solve_part1(input: &str) -> usize {
     let (sorter_str, parts_str) = input.split_once("\n\n").unwrap();
@@ -10,68 +13,106 @@ fn solve_part1(input: &str) -> usize {
     parts_str
         .lines()
         .map(Part::from)
-        .filter(|part| sorter.part_accepted(*part))
-        .map(Part::rating)
+        .filter(|part| sorter.part_accepted(part))
+        .map(|part| part.rating())
         .sum()
 }
 
 #[aoc(day19, part2)]
 fn solve_part2(input: &str) -> usize {
+    accepted_combinations(input, RATING_BOUNDS)
+}
+
+#[aoc(day19, part2, Regions)]
+fn solve_part2_regions(input: &str) -> usize {
+    let sorter_str = input.split("\n\n").next().unwrap();
+    let mut sorter = Sorter::from(sorter_str);
+    sorter.simplify(RATING_BOUNDS);
+
+    sorter
+        .accepted_regions(&parse_parts(input), RATING_BOUNDS)
+        .iter()
+        .map(PartRange::combinations)
+        .sum()
+}
+
+/// The number of accepted parts when every category can take any rating within `bounds`
+pub fn accepted_combinations(input: &str, bounds: RangeInclusive<usize>) -> usize {
     let sorter_str = input.split("\n\n").next().unwrap();
     let sorter = Sorter::from(sorter_str);
 
     sorter
-        .accepted_ranges()
+        .accepted_ranges(sorter.full_range(&parse_parts(input), bounds))
         .iter()
         .map(PartRange::combinations)
         .sum()
 }
 
-#[derive(Clone, Copy)]
-struct Part {
-    x: usize,
-    m: usize,
-    a: usize,
-    s: usize,
+/// Rewrites the workflows with redundant rules dropped and simple workflows inlined
+pub fn canonical_workflows(input: &str, bounds: RangeInclusive<usize>) -> String {
+    let sorter_str = input.split("\n\n").next().unwrap();
+    let mut sorter = Sorter::from(sorter_str);
+    sorter.simplify(bounds);
+
+    sorter.to_string()
+}
+
+/// Lists the accepted parts as disjoint boxes, one per line, with adjacent boxes merged
+pub fn accepted_regions(input: &str, bounds: RangeInclusive<usize>) -> String {
+    let sorter_str = input.split("\n\n").next().unwrap();
+    let mut sorter = Sorter::from(sorter_str);
+    sorter.simplify(bounds.clone());
+
+    sorter
+        .accepted_regions(&parse_parts(input), bounds)
+        .iter()
+        .map(PartRange::to_string)
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
-impl Part {
-    fn rating(self) -> usize {
-        self.x + self.m + self.a + self.s
+fn parse_parts(input: &str) -> Vec<Part<'_>> {
+    match input.split_once("\n\n") {
+        Some((_, parts_str)) => parts_str.lines().map(Part::from).collect(),
+        None => Vec::new(),
     }
 }
 
-impl From<&str> for Part {
-    fn from(value: &str) -> Self {
-        let mut part = Part {
-            x: 0,
-            m: 0,
-            a: 0,
-            s: 0,
-        };
-        let pieces = value
+struct Part<'a> {
+    ratings: HashMap<&'a str, usize>,
+}
+
+impl<'a> Part<'a> {
+    fn rating(&self) -> usize {
+        self.ratings.values().sum()
+    }
+
+    fn get(&self, category: &str) -> usize {
+        match self.ratings.get(category) {
+            Some(value) => *value,
+            None => panic!("Part has no rating for category '{}'", category),
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Part<'a> {
+    fn from(value: &'a str) -> Self {
+        let ratings = value
             .trim_start_matches('{')
             .trim_end_matches('}')
-            .split(',');
-
-        for piece in pieces {
-            let (key, value_str) = piece.split_once('=').unwrap();
-            let value = value_str.parse().unwrap();
-
-            match key {
-                "x" => part.x = value,
-                "m" => part.m = value,
-                "a" => part.a = value,
-                "s" => part.s = value,
-                _ => unreachable!(),
-            }
-        }
+            .split(',')
+            .map(|piece| {
+                let (key, value_str) = piece.split_once('=').unwrap();
 
-        part
+                (key, value_str.parse().unwrap())
+            })
+            .collect();
+
+        Self { ratings }
     }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq)]
 enum Target<'a> {
     Accepted,
     Rejected,
@@ -88,62 +129,71 @@ impl<'a> From<&'a str> for Target<'a> {
     }
 }
 
-#[derive(Clone, Copy)]
-enum Condition {
-    XLess(usize),
-    XGreater(usize),
-    MLess(usize),
-    MGreater(usize),
-    ALess(usize),
-    AGreater(usize),
-    SLess(usize),
-    SGreater(usize),
+impl fmt::Display for Target<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Target::Accepted => write!(f, "A"),
+            Target::Rejected => write!(f, "R"),
+            Target::Workflow(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Condition<'a> {
+    Less(&'a str, usize),
+    Greater(&'a str, usize),
     Always,
 }
 
-impl Condition {
-    fn applies(self, part: Part) -> bool {
-        use Condition::*;
+impl<'a> Condition<'a> {
+    fn applies(self, part: &Part) -> bool {
+        match self {
+            Condition::Less(category, cmp) => part.get(category) < cmp,
+            Condition::Greater(category, cmp) => part.get(category) > cmp,
+            Condition::Always => true,
+        }
+    }
 
+    fn category(self) -> Option<&'a str> {
         match self {
-            XLess(cmp) => part.x < cmp,
-            XGreater(cmp) => part.x > cmp,
-            MLess(cmp) => part.m < cmp,
-            MGreater(cmp) => part.m > cmp,
-            ALess(cmp) => part.a < cmp,
-            AGreater(cmp) => part.a > cmp,
-            SLess(cmp) => part.s < cmp,
-            SGreater(cmp) => part.s > cmp,
-            Always => true,
+            Condition::Less(category, _) | Condition::Greater(category, _) => Some(category),
+            Condition::Always => None,
         }
     }
 }
 
-impl From<&str> for Condition {
-    fn from(value: &str) -> Self {
-        let (condition, number_str) = value.split_at(2);
+impl<'a> From<&'a str> for Condition<'a> {
+    fn from(value: &'a str) -> Self {
+        let split = value.find(['<', '>']).unwrap();
+        let (category, rest) = value.split_at(split);
+        let (operator, number_str) = rest.split_at(1);
         let number = number_str.parse().unwrap();
 
-        match condition {
-            "x<" => Self::XLess(number),
-            "x>" => Self::XGreater(number),
-            "m<" => Self::MLess(number),
-            "m>" => Self::MGreater(number),
-            "a<" => Self::ALess(number),
-            "a>" => Self::AGreater(number),
-            "s<" => Self::SLess(number),
-            "s>" => Self::SGreater(number),
+        match operator {
+            "<" => Self::Less(category, number),
+            ">" => Self::Greater(category, number),
             _ => unreachable!(),
         }
     }
 }
 
+impl fmt::Display for Condition<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Condition::Less(category, cmp) => write!(f, "{}<{}", category, cmp),
+            Condition::Greater(category, cmp) => write!(f, "{}>{}", category, cmp),
+            Condition::Always => Ok(()),
+        }
+    }
+}
+
 struct Workflow<'a> {
-    predicates: Vec<(Condition, Target<'a>)>,
+    predicates: Vec<(Condition<'a>, Target<'a>)>,
 }
 
 impl<'a> Workflow<'a> {
-    fn apply(&self, part: Part) -> Target<'a> {
+    fn apply(&self, part: &Part) -> Target<'a> {
         for (condition, target) in &self.predicates {
             if condition.applies(part) {
                 return *target;
@@ -152,6 +202,70 @@ impl<'a> Workflow<'a> {
 
         unreachable!()
     }
+
+    fn targets(&self) -> impl Iterator<Item = Target<'a>> + '_ {
+        self.predicates.iter().map(|(_, target)| *target)
+    }
+
+    /// Drops rules that can never match or never change the outcome
+    fn prune(&mut self, full_range: &PartRange<'a>) -> bool {
+        let mut predicates = Vec::new();
+        let mut active_range = full_range.clone();
+
+        for (condition, target) in &self.predicates {
+            let (matched, no_matched) = active_range.split_condition(*condition);
+
+            if matched.is_none() {
+                continue;
+            }
+
+            match no_matched {
+                Some(no_matched_range) => {
+                    predicates.push((*condition, *target));
+                    active_range = no_matched_range;
+                }
+                None => {
+                    predicates.push((Condition::Always, *target));
+                    break;
+                }
+            }
+        }
+
+        while predicates.len() > 1
+            && predicates[predicates.len() - 2].1 == predicates[predicates.len() - 1].1
+        {
+            predicates.remove(predicates.len() - 2);
+        }
+
+        let changed = predicates != self.predicates;
+        self.predicates = predicates;
+        changed
+    }
+
+    fn replace_target(&mut self, from: Target<'a>, to: Target<'a>) {
+        for (_, target) in &mut self.predicates {
+            if *target == from {
+                *target = to;
+            }
+        }
+    }
+}
+
+impl fmt::Display for Workflow<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (condition, target)) in self.predicates.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+
+            match condition {
+                Condition::Always => write!(f, "{}", target)?,
+                _ => write!(f, "{}:{}", condition, target)?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<'a> From<&'a str> for Workflow<'a> {
@@ -173,7 +287,7 @@ struct Sorter<'a> {
 }
 
 impl<'a> Sorter<'a> {
-    fn part_accepted(&self, part: Part) -> bool {
+    fn part_accepted(&self, part: &Part) -> bool {
         let mut current = Target::Workflow("in");
 
         while let Target::Workflow(id) = current {
@@ -185,10 +299,158 @@ impl<'a> Sorter<'a> {
         matches!(current, Target::Accepted)
     }
 
-    fn accepted_ranges(&self) -> Vec<PartRange> {
+    fn categories(&self) -> BTreeSet<&'a str> {
+        self.workflows
+            .values()
+            .flat_map(|workflow| &workflow.predicates)
+            .filter_map(|(condition, _)| condition.category())
+            .collect()
+    }
+
+    /// Every category tested by a condition or rated on one of `parts`, rated within `bounds`
+    fn full_range(&self, parts: &[Part<'a>], bounds: RangeInclusive<usize>) -> PartRange<'a> {
+        let mut categories = self.categories();
+        categories.extend(parts.iter().flat_map(|part| part.ratings.keys()));
+
+        PartRange::new(
+            categories
+                .into_iter()
+                .map(|category| (category, bounds.clone())),
+        )
+    }
+
+    /// Workflow ids in the order they are first reached from `in`
+    fn reachable(&self) -> Vec<&'a str> {
+        let mut order = Vec::new();
+        let mut seen = HashSet::new();
+        let mut queue = VecDeque::from([self.workflows.get_key_value("in").unwrap().0]);
+
+        while let Some(id) = queue.pop_front() {
+            if !seen.insert(*id) {
+                continue;
+            }
+            order.push(*id);
+
+            for target in self.workflows.get(id).unwrap().targets() {
+                if let Target::Workflow(next) = target {
+                    queue.push_back(self.workflows.get_key_value(next).unwrap().0);
+                }
+            }
+        }
+
+        order
+    }
+
+    fn simplify(&mut self, bounds: RangeInclusive<usize>) {
+        let full_range = self.full_range(&[], bounds);
+
+        loop {
+            let mut changed = false;
+
+            for workflow in self.workflows.values_mut() {
+                changed |= workflow.prune(&full_range);
+            }
+
+            changed |= self.collapse_trivial();
+            changed |= self.inline_single_use();
+
+            if !changed {
+                break;
+            }
+        }
+
+        let reachable: HashSet<_> = self.reachable().into_iter().collect();
+        self.workflows.retain(|id, _| reachable.contains(id));
+    }
+
+    fn collapse_trivial(&mut self) -> bool {
+        let trivial =
+            self.workflows
+                .iter()
+                .find_map(|(id, workflow)| match workflow.predicates.as_slice() {
+                    [(Condition::Always, target)] if *id != "in" => Some((*id, *target)),
+                    _ => None,
+                });
+
+        let Some((id, target)) = trivial else {
+            return false;
+        };
+
+        self.workflows.remove(id);
+        for workflow in self.workflows.values_mut() {
+            workflow.replace_target(Target::Workflow(id), target);
+        }
+
+        true
+    }
+
+    /// Merges a workflow into its parent when the parent's fallback is the only reference to it
+    fn inline_single_use(&mut self) -> bool {
+        let mut references: HashMap<&str, usize> = HashMap::new();
+        for target in self.workflows.values().flat_map(Workflow::targets) {
+            if let Target::Workflow(id) = target {
+                *references.entry(id).or_default() += 1;
+            }
+        }
+
+        let inlined =
+            self.workflows
+                .iter()
+                .find_map(|(parent, workflow)| match workflow.predicates.last() {
+                    Some((_, Target::Workflow(child)))
+                        if *child != "in" && *child != *parent && references[child] == 1 =>
+                    {
+                        Some((*parent, *child))
+                    }
+                    _ => None,
+                });
+
+        let Some((parent, child)) = inlined else {
+            return false;
+        };
+
+        let child_workflow = self.workflows.remove(child).unwrap();
+        let parent_workflow = self.workflows.get_mut(parent).unwrap();
+        parent_workflow.predicates.pop();
+        parent_workflow.predicates.extend(child_workflow.predicates);
+
+        true
+    }
+
+    fn accepted_regions(
+        &self,
+        parts: &[Part<'a>],
+        bounds: RangeInclusive<usize>,
+    ) -> Vec<PartRange<'a>> {
+        let mut regions = self.accepted_ranges(self.full_range(parts, bounds));
+
+        'merging: loop {
+            for i in 0..regions.len() {
+                for j in (i + 1)..regions.len() {
+                    if let Some(merged) = regions[i].merge(&regions[j]) {
+                        regions[i] = merged;
+                        regions.swap_remove(j);
+                        continue 'merging;
+                    }
+                }
+            }
+
+            break;
+        }
+
+        regions.sort_by(|a, b| {
+            a.ranges
+                .values()
+                .map(RangeInclusive::start)
+                .cmp(b.ranges.values().map(RangeInclusive::start))
+        });
+        regions
+    }
+
+    fn accepted_ranges(&self, full_range: PartRange<'a>) -> Vec<PartRange<'a>> {
         let mut accepted = Vec::new();
         let mut ranges = VecDeque::new();
-        ranges.push_back((PartRange::new(), Target::Workflow("in")));
+        ranges.push_back((full_range, Target::Workflow("in")));
 
         while let Some((range, target)) = ranges.pop_front() {
             match target {
@@ -235,236 +497,232 @@ impl<'a> From<&'a str> for Sorter<'a> {
     }
 }
 
-#[derive(Clone)]
-struct PartRange {
-    x: RangeInclusive<usize>,
-    m: RangeInclusive<usize>,
-    a: RangeInclusive<usize>,
-    s: RangeInclusive<usize>,
+impl fmt::Display for Sorter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, id) in self.reachable().into_iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+
+            write!(f, "{}{{{}}}", id, self.workflows.get(id).unwrap())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Eq)]
+struct PartRange<'a> {
+    ranges: BTreeMap<&'a str, RangeInclusive<usize>>,
 }
 
-impl PartRange {
-    fn new() -> Self {
+impl<'a> PartRange<'a> {
+    fn new<I>(ranges: I) -> Self
+    where
+        I: IntoIterator<Item = (&'a str, RangeInclusive<usize>)>,
+    {
         Self {
-            x: 1..=4000,
-            m: 1..=4000,
-            a: 1..=4000,
-            s: 1..=4000,
+            ranges: ranges.into_iter().collect(),
         }
     }
 
-    fn split_condition(&self, condition: Condition) -> (Option<Self>, Option<Self>) {
+    fn with_range(&self, category: &'a str, range: RangeInclusive<usize>) -> Self {
+        let mut ranges = self.ranges.clone();
+        ranges.insert(category, range);
+
+        Self { ranges }
+    }
+
+    fn get(&self, category: &str) -> &RangeInclusive<usize> {
+        match self.ranges.get(category) {
+            Some(range) => range,
+            None => panic!("PartRange has no bounds for category '{}'", category),
+        }
+    }
+
+    fn split_condition(&self, condition: Condition<'a>) -> (Option<Self>, Option<Self>) {
         match condition {
             Condition::Always => (Some(self.clone()), None),
-            Condition::XLess(cmp) => {
-                let x_start = *self.x.start();
-                let x_end = *self.x.end();
+            Condition::Less(category, cmp) => {
+                let start = *self.get(category).start();
+                let end = *self.get(category).end();
 
-                if cmp <= x_start {
+                if cmp <= start {
                     (None, Some(self.clone()))
-                } else if cmp <= x_end {
+                } else if cmp <= end {
                     (
-                        Some(Self {
-                            x: x_start..=(cmp - 1),
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: cmp..=x_end,
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
+                        Some(self.with_range(category, start..=(cmp - 1))),
+                        Some(self.with_range(category, cmp..=end)),
                     )
                 } else {
                     (Some(self.clone()), None)
                 }
             }
-            Condition::XGreater(cmp) => {
-                let x_start = *self.x.start();
-                let x_end = *self.x.end();
+            Condition::Greater(category, cmp) => {
+                let start = *self.get(category).start();
+                let end = *self.get(category).end();
 
-                if cmp >= x_end {
+                if cmp >= end {
                     (None, Some(self.clone()))
-                } else if cmp >= x_start {
+                } else if cmp >= start {
                     (
-                        Some(Self {
-                            x: (cmp + 1)..=x_end,
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: x_start..=cmp,
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
+                        Some(self.with_range(category, (cmp + 1)..=end)),
+                        Some(self.with_range(category, start..=cmp)),
                     )
                 } else {
                     (Some(self.clone()), None)
                 }
             }
-            Condition::MLess(cmp) => {
-                let m_start = *self.m.start();
-                let m_end = *self.m.end();
+        }
+    }
 
-                if cmp <= m_start {
-                    (None, Some(self.clone()))
-                } else if cmp <= m_end {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: m_start..=(cmp - 1),
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: cmp..=m_end,
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
-            }
-            Condition::MGreater(cmp) => {
-                let m_start = *self.m.start();
-                let m_end = *self.m.end();
+    /// Joins two ranges that differ only in one category, where they are adjacent
+    fn merge(&self, other: &Self) -> Option<Self> {
+        let mut differing = self
+            .ranges
+            .iter()
+            .filter(|(category, range)| other.ranges.get(*category) != Some(*range));
 
-                if cmp >= m_end {
-                    (None, Some(self.clone()))
-                } else if cmp >= m_start {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: (cmp + 1)..=m_end,
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: m_start..=cmp,
-                            a: self.a.clone(),
-                            s: self.s.clone(),
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
-            }
-            Condition::ALess(cmp) => {
-                let a_start = *self.a.start();
-                let a_end = *self.a.end();
+        let (category, range) = differing.next()?;
+        if differing.next().is_some() {
+            return None;
+        }
 
-                if cmp <= a_start {
-                    (None, Some(self.clone()))
-                } else if cmp <= a_end {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: a_start..=(cmp - 1),
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: cmp..=a_end,
-                            s: self.s.clone(),
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
-            }
-            Condition::AGreater(cmp) => {
-                let a_start = *self.a.start();
-                let a_end = *self.a.end();
+        let other_range = other.ranges.get(category)?;
+        let merged = if range.end() + 1 == *other_range.start() {
+            *range.start()..=*other_range.end()
+        } else if other_range.end() + 1 == *range.start() {
+            *other_range.start()..=*range.end()
+        } else {
+            return None;
+        };
 
-                if cmp >= a_end {
-                    (None, Some(self.clone()))
-                } else if cmp >= a_start {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: (cmp + 1)..=a_end,
-                            s: self.s.clone(),
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: a_start..=cmp,
-                            s: self.s.clone(),
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
-            }
-            Condition::SLess(cmp) => {
-                let s_start = *self.s.start();
-                let s_end = *self.s.end();
+        Some(self.with_range(category, merged))
+    }
 
-                if cmp <= s_start {
-                    (None, Some(self.clone()))
-                } else if cmp <= s_end {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: s_start..=(cmp - 1),
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: cmp..=s_end,
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
-            }
-            Condition::SGreater(cmp) => {
-                let s_start = *self.s.start();
-                let s_end = *self.s.end();
+    fn combinations(&self) -> usize {
+        self.ranges
+            .values()
+            .map(|range| range.end() + 1 - range.start())
+            .product()
+    }
+}
 
-                if cmp >= s_end {
-                    (None, Some(self.clone()))
-                } else if cmp >= s_start {
-                    (
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: (cmp + 1)..=s_end,
-                        }),
-                        Some(Self {
-                            x: self.x.clone(),
-                            m: self.m.clone(),
-                            a: self.a.clone(),
-                            s: s_start..=cmp,
-                        }),
-                    )
-                } else {
-                    (Some(self.clone()), None)
-                }
+impl fmt::Display for PartRange<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ranges: Vec<_> = self
+            .ranges
+            .iter()
+            .map(|(category, range)| format!("{}={}..={}", category, range.start(), range.end()))
+            .collect();
+
+        write!(f, "{{{}}}", ranges.join(","))
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "px{a<2006:qkq,m>2090:A,rfg}
+pv{a>1716:R,A}
+lnx{m>1548:A,A}
+rfg{s<537:gd,x>2440:R,A}
+qs{s>3448:A,lnx}
+qkq{x<1416:A,crn}
+crn{x>2662:A,R}
+in{s<1351:px,qqz}
+qqz{s>2770:qs,m<1801:hdj,R}
+gd{a>3333:R,R}
+hdj{m>838:A,pv}
+
+{x=787,m=2655,a=1222,s=2876}
+{x=1679,m=44,a=2067,s=496}
+{x=2036,m=264,a=79,s=2244}
+{x=2461,m=1339,a=466,s=291}
+{x=2127,m=1623,a=2188,s=1013}";
+
+#[test]
+fn test_part1() {
+    assert_eq!(19114, solve_part1(EXAMPLE));
+}
+
+#[test]
+fn test_part2() {
+    assert_eq!(167409079868000, solve_part2(EXAMPLE));
+}
+
+#[test]
+fn test_custom_categories() {
+    let workflows = "in{weight<10:A,colour>2:heavy,R}\nheavy{weight>50:R,A}";
+    let sorter = Sorter::from(workflows);
+
+    // Only the categories the workflows mention are counted
+    let full_range = sorter.full_range(&[], 1..=100);
+    assert_eq!(100 * 100, full_range.combinations());
+
+    // weight 1..=9 (any colour) + weight 10..=50 with colour 3..=100
+    assert_eq!(9 * 100 + 41 * 98, accepted_combinations(workflows, 1..=100));
+    // With ratings of at most 5, every weight passes the first rule
+    assert_eq!(5 * 5, accepted_combinations(workflows, 1..=5));
+
+    let part = Part::from("{weight=20,colour=4}");
+    assert!(sorter.part_accepted(&part));
+    assert_eq!(24, part.rating());
+}
+
+#[test]
+fn test_unreferenced_categories() {
+    let input = "in{x<2:A,R}\n\n{x=1,m=1,a=1,s=1}";
+    let sorter = Sorter::from(input.split("\n\n").next().unwrap());
+
+    let full_range = sorter.full_range(&parse_parts(input), RATING_BOUNDS);
+    assert_eq!(4000usize.pow(4), full_range.combinations());
+    assert_eq!(4000usize.pow(3), solve_part2(input));
+    assert_eq!(solve_part2(input), solve_part2_regions(input));
+}
+
+#[test]
+fn test_canonical_round_trip() {
+    let canonical = canonical_workflows(EXAMPLE, RATING_BOUNDS);
+    let original = Sorter::from(EXAMPLE.split("\n\n").next().unwrap());
+    let simplified = Sorter::from(canonical.as_str());
+
+    assert!(simplified.workflows.len() < original.workflows.len());
+    assert_eq!(canonical, canonical_workflows(&canonical, RATING_BOUNDS));
+
+    for x in (1..=4000).step_by(97) {
+        for m in (1..=4000).step_by(89) {
+            for (a, s) in [
+                (1, 4000),
+                (1716, 537),
+                (2006, 1351),
+                (3334, 2771),
+                (4000, 1),
+            ] {
+                let part_str = format!("{{x={},m={},a={},s={}}}", x, m, a, s);
+                let part = Part::from(part_str.as_str());
+                assert_eq!(
+                    original.part_accepted(&part),
+                    simplified.part_accepted(&part)
+                );
             }
         }
     }
 
-    fn combinations(&self) -> usize {
-        let x_range = *self.x.end() - *self.x.start() + 1;
-        let m_range = *self.m.end() - *self.m.start() + 1;
-        let a_range = *self.a.end() - *self.a.start() + 1;
-        let s_range = *self.s.end() - *self.s.start() + 1;
+    let parts = EXAMPLE.split_once("\n\n").unwrap().1;
+    let reparsed = format!("{}\n\n{}", canonical, parts);
+    assert_eq!(solve_part1(EXAMPLE), solve_part1(&reparsed));
+    assert_eq!(solve_part2(EXAMPLE), solve_part2(&reparsed));
+}
 
-        x_range * m_range * a_range * s_range
-    }
+#[test]
+fn test_accepted_regions() {
+    let regions = accepted_regions(EXAMPLE, RATING_BOUNDS);
+    let sorter = Sorter::from(EXAMPLE.split("\n\n").next().unwrap());
+
+    assert!(
+        regions.lines().count()
+            < sorter
+                .accepted_ranges(sorter.full_range(&[], RATING_BOUNDS))
+                .len()
+    );
+    assert_eq!(solve_part2(EXAMPLE), solve_part2_regions(EXAMPLE));
 }
@@ -0,0 +1,120 @@
+/// Dijkstra's algorithm with a ring of buckets for small integer costs (Dial's algorithm).
+/// `index` must map every value below the `states` count given to `new`.
+pub struct Dial<V, FI, FS, FN> {
+    buckets: Vec<Vec<V>>,
+    cost: usize,
+    queued: usize,
+    visited: Vec<bool>,
+    index: FI,
+    success: FS,
+    neighbors: FN,
+}
+
+impl<V, FI, FS, FN, I> Dial<V, FI, FS, FN>
+where
+    FI: FnMut(&V) -> usize,
+    FS: FnMut(&V) -> bool,
+    FN: FnMut(&V) -> I,
+    I: IntoIterator<Item = (V, usize)>,
+{
+    /// Move costs from `neighbors` must be at most `max_cost`, or the ring would wrap around
+    pub fn new(
+        start: V,
+        max_cost: usize,
+        states: usize,
+        index: FI,
+        success: FS,
+        neighbors: FN,
+    ) -> Self {
+        let mut buckets: Vec<_> = (0..=max_cost).map(|_| Vec::new()).collect();
+        buckets[0].push(start);
+
+        Self {
+            buckets,
+            cost: 0,
+            queued: 1,
+            visited: vec![false; states],
+            index,
+            success,
+            neighbors,
+        }
+    }
+}
+
+impl<V, FI, FS, FN, I> Iterator for Dial<V, FI, FS, FN>
+where
+    FI: FnMut(&V) -> usize,
+    FS: FnMut(&V) -> bool,
+    FN: FnMut(&V) -> I,
+    I: IntoIterator<Item = (V, usize)>,
+{
+    type Item = (V, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.queued > 0 {
+            let slot = self.cost % self.buckets.len();
+
+            let Some(value) = self.buckets[slot].pop() else {
+                self.cost += 1;
+                continue;
+            };
+            self.queued -= 1;
+
+            let id = (self.index)(&value);
+            if self.visited[id] {
+                continue;
+            }
+
+            self.visited[id] = true;
+
+            if (self.success)(&value) {
+                return Some((value, self.cost));
+            }
+
+            for (neighbor, move_cost) in (self.neighbors)(&value) {
+                debug_assert!(
+                    move_cost < self.buckets.len(),
+                    "Move cost {} exceeds the maximum cost {}",
+                    move_cost,
+                    self.buckets.len() - 1
+                );
+
+                if !self.visited[(self.index)(&neighbor)] {
+                    let slot = (self.cost + move_cost) % self.buckets.len();
+                    self.buckets[slot].push(neighbor);
+                    self.queued += 1;
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[test]
+fn test_small_weights() {
+    // A line of 5 nodes where skipping ahead two costs 3 and stepping one costs 2
+    let costs: Vec<_> = Dial::new(
+        0usize,
+        3,
+        5,
+        |node| *node,
+        |node| *node >= 3,
+        |node| {
+            let node = *node;
+            [(node + 1, 2), (node + 2, 3)]
+                .into_iter()
+                .filter(|(next, _)| *next < 5)
+        },
+    )
+    .collect();
+
+    assert_eq!(vec![(3, 5), (4, 6)], costs);
+}
+
+#[test]
+#[should_panic(expected = "exceeds the maximum cost")]
+#[cfg(debug_assertions)]
+fn test_cost_above_maximum() {
+    let _ = Dial::new(0usize, 2, 2, |node| *node, |node| *node == 1, |_| [(1, 3)]).count();
+}
@@ -0,0 +1,96 @@
+/// A simple polygon on the integer lattice, with vertices in either order
+pub struct Polygon {
+    vertices: Vec<(i128, i128)>,
+}
+
+impl Polygon {
+    pub fn new<I>(vertices: I) -> Self
+    where
+        I: IntoIterator<Item = (i128, i128)>,
+    {
+        Self {
+            vertices: vertices.into_iter().collect(),
+        }
+    }
+
+    fn edges(&self) -> impl Iterator<Item = ((i128, i128), (i128, i128))> + '_ {
+        self.vertices
+            .iter()
+            .zip(self.vertices.iter().cycle().skip(1))
+            .map(|(first, second)| (*first, *second))
+    }
+
+    /// Twice the enclosed area, from the shoelace formula
+    pub fn doubled_area(&self) -> Option<i128> {
+        let mut pair_sums: i128 = 0;
+
+        for ((x1, y1), (x2, y2)) in self.edges() {
+            let term = x1.checked_mul(y2)?.checked_sub(y1.checked_mul(x2)?)?;
+            pair_sums = pair_sums.checked_add(term)?;
+        }
+
+        pair_sums.checked_abs()
+    }
+
+    pub fn boundary_points(&self) -> Option<i128> {
+        let mut count: i128 = 0;
+
+        for ((x1, y1), (x2, y2)) in self.edges() {
+            let dx = x2.checked_sub(x1)?.checked_abs()?;
+            let dy = y2.checked_sub(y1)?.checked_abs()?;
+            count = count.checked_add(gcd(dx, dy))?;
+        }
+
+        Some(count)
+    }
+
+    /// Pick's theorem: A = I + B/2 - 1, so I = (2A - B + 2) / 2
+    pub fn interior_points(&self) -> Option<i128> {
+        let doubled_area = self.doubled_area()?;
+        let boundary = self.boundary_points()?;
+
+        Some(doubled_area.checked_sub(boundary)?.checked_add(2)? / 2)
+    }
+
+    pub fn enclosed_points(&self) -> Option<i128> {
+        self.interior_points()?.checked_add(self.boundary_points()?)
+    }
+}
+
+pub fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[test]
+fn test_orientation() {
+    let clockwise = Polygon::new([(0, 0), (0, 4), (3, 4), (3, 0)]);
+    let counter_clockwise = Polygon::new([(0, 0), (3, 0), (3, 4), (0, 4), (0, 0)]);
+
+    for polygon in [clockwise, counter_clockwise] {
+        assert_eq!(Some(24), polygon.doubled_area());
+        assert_eq!(Some(14), polygon.boundary_points());
+        assert_eq!(Some(6), polygon.interior_points());
+        assert_eq!(Some(20), polygon.enclosed_points());
+    }
+}
+
+#[test]
+fn test_diagonal_edges() {
+    let triangle = Polygon::new([(0, 0), (4, 0), (0, 6)]);
+
+    assert_eq!(Some(24), triangle.doubled_area());
+    assert_eq!(Some(4 + 2 + 6), triangle.boundary_points());
+    assert_eq!(Some(7), triangle.interior_points());
+}
+
+#[test]
+fn test_overflow() {
+    let huge = Polygon::new([(0, 0), (i128::MAX, 0), (i128::MAX, i128::MAX)]);
+
+    assert_eq!(None, huge.doubled_area());
+    assert_eq!(None, huge.interior_points());
+}
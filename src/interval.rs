@@ -0,0 +1,228 @@
+/// An inclusive range, so ranges up to `usize::MAX` don't overflow
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Interval {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Interval {
+    pub fn new(start: usize, end: usize) -> Self {
+        assert!(start <= end, "Interval start must not be after its end");
+
+        Self { start, end }
+    }
+
+    pub fn with_length(start: usize, length: usize) -> Option<Self> {
+        let last = length.checked_sub(1)?;
+
+        Some(Self::new(start, start.saturating_add(last)))
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, value: usize) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+
+        (start <= end).then(|| Self::new(start, end))
+    }
+}
+
+/// Sorted, disjoint intervals, with overlapping or adjacent intervals always coalesced
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Interval> {
+        self.intervals.iter()
+    }
+
+    #[cfg(test)]
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    #[cfg(test)]
+    pub fn contains(&self, value: usize) -> bool {
+        let index = self
+            .intervals
+            .partition_point(|interval| interval.end < value);
+
+        self.intervals
+            .get(index)
+            .is_some_and(|interval| interval.contains(value))
+    }
+
+    pub fn min(&self) -> Option<usize> {
+        self.intervals.first().map(|interval| interval.start)
+    }
+
+    pub fn insert(&mut self, interval: Interval) {
+        self.extend([interval]);
+    }
+
+    #[cfg(test)]
+    pub fn union(&self, other: &Self) -> Self {
+        self.intervals
+            .iter()
+            .chain(&other.intervals)
+            .copied()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let mut ours = self.intervals.iter().peekable();
+        let mut theirs = other.intervals.iter().peekable();
+
+        while let (Some(a), Some(b)) = (ours.peek(), theirs.peek()) {
+            intervals.extend(a.intersection(b));
+
+            // Whichever interval finishes first can't overlap anything else in the other set
+            if a.end < b.end {
+                ours.next();
+            } else {
+                theirs.next();
+            }
+        }
+
+        Self { intervals }
+    }
+
+    pub fn difference(&self, other: &Self) -> Self {
+        let mut intervals = Vec::new();
+        let mut removed = other.intervals.iter().copied().peekable();
+
+        for interval in &self.intervals {
+            let mut current = Some(*interval);
+
+            while let (Some(remaining), Some(next)) = (current, removed.peek().copied()) {
+                if next.end < remaining.start {
+                    removed.next();
+                    continue;
+                }
+
+                if next.start > remaining.end {
+                    break;
+                }
+
+                if next.start > remaining.start {
+                    intervals.push(Interval::new(remaining.start, next.start - 1));
+                }
+
+                current = if next.end < remaining.end {
+                    removed.next();
+                    Some(Interval::new(next.end + 1, remaining.end))
+                } else {
+                    None
+                };
+            }
+
+            intervals.extend(current);
+        }
+
+        Self { intervals }
+    }
+}
+
+impl FromIterator<Interval> for IntervalSet {
+    fn from_iter<T: IntoIterator<Item = Interval>>(iter: T) -> Self {
+        let mut sorted: Vec<_> = iter.into_iter().collect();
+        sorted.sort_by_key(|interval| interval.start);
+
+        let mut intervals: Vec<Interval> = Vec::with_capacity(sorted.len());
+        for interval in sorted {
+            match intervals.last_mut() {
+                Some(last) if interval.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(interval.end);
+                }
+                _ => intervals.push(interval),
+            }
+        }
+
+        Self { intervals }
+    }
+}
+
+impl Extend<Interval> for IntervalSet {
+    fn extend<T: IntoIterator<Item = Interval>>(&mut self, iter: T) {
+        *self = self.intervals.drain(..).chain(iter).collect();
+    }
+}
+
+#[test]
+fn test_coalescing() {
+    let set: IntervalSet = [
+        Interval::new(10, 20),
+        Interval::new(0, 4),
+        Interval::new(5, 7),
+        Interval::new(15, 30),
+    ]
+    .into_iter()
+    .collect();
+
+    assert_eq!(
+        vec![&Interval::new(0, 7), &Interval::new(10, 30)],
+        set.iter().collect::<Vec<_>>()
+    );
+    assert!(set.contains(7));
+    assert!(!set.contains(8));
+    assert_eq!(Some(0), set.min());
+}
+
+#[test]
+fn test_set_algebra() {
+    let a: IntervalSet = [Interval::new(0, 10), Interval::new(20, 30)]
+        .into_iter()
+        .collect();
+    let b: IntervalSet = [Interval::new(5, 24), Interval::new(28, 40)]
+        .into_iter()
+        .collect();
+
+    let union: IntervalSet = [Interval::new(0, 40)].into_iter().collect();
+    let intersection: IntervalSet = [
+        Interval::new(5, 10),
+        Interval::new(20, 24),
+        Interval::new(28, 30),
+    ]
+    .into_iter()
+    .collect();
+    let difference: IntervalSet = [Interval::new(0, 4), Interval::new(25, 27)]
+        .into_iter()
+        .collect();
+
+    assert_eq!(union, a.union(&b));
+    assert_eq!(intersection, a.intersection(&b));
+    assert_eq!(intersection, b.intersection(&a));
+    assert_eq!(difference, a.difference(&b));
+    assert!(a.difference(&a).is_empty());
+}
+
+#[test]
+fn test_extremes() {
+    let everything: IntervalSet = [Interval::new(0, usize::MAX)].into_iter().collect();
+    let top = Interval::with_length(usize::MAX - 9, 10).unwrap();
+
+    assert_eq!(Interval::new(usize::MAX - 9, usize::MAX), top);
+    assert_eq!(None, Interval::with_length(3, 0));
+
+    let mut set = IntervalSet::new();
+    set.insert(top);
+    set.insert(Interval::new(0, usize::MAX - 10));
+    assert_eq!(everything, set);
+
+    let rest = everything.difference(&[top].into_iter().collect());
+    assert_eq!(
+        vec![&Interval::new(0, usize::MAX - 10)],
+        rest.iter().collect::<Vec<_>>()
+    );
+}
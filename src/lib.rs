@@ -24,6 +24,11 @@ pub mod day_23;
 pub mod day_24;
 pub mod day_25;
 
+mod cycle;
+mod dial;
 mod dijkstra;
+mod geometry;
+mod interval;
+mod stream;
 
 aoc_runner_derive::aoc_lib! { year = 2023 }
@@ -0,0 +1,75 @@
+use std::io::{self, BufRead};
+
+/// Folds `f` over each line of `reader` without its line ending, reusing one buffer
+pub fn fold_lines<R, T, F>(mut reader: R, init: T, mut f: F) -> io::Result<T>
+where
+    R: BufRead,
+    F: FnMut(T, &str) -> T,
+{
+    let mut accumulator = init;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(accumulator);
+        }
+
+        accumulator = f(accumulator, line.trim_end_matches(['\n', '\r']));
+    }
+}
+
+/// The same text over and over, for testing the streaming solvers on large inputs
+#[cfg(test)]
+pub struct Repeat {
+    text: &'static [u8],
+    offset: usize,
+    remaining: usize,
+}
+
+#[cfg(test)]
+impl Repeat {
+    pub fn new(text: &'static str, times: usize) -> Self {
+        Self {
+            text: text.as_bytes(),
+            offset: 0,
+            remaining: times,
+        }
+    }
+}
+
+#[cfg(test)]
+impl io::Read for Repeat {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Ok(0);
+        }
+
+        let count = buf.len().min(self.text.len() - self.offset);
+        buf[..count].copy_from_slice(&self.text[self.offset..self.offset + count]);
+        self.offset += count;
+
+        if self.offset == self.text.len() {
+            self.offset = 0;
+            self.remaining -= 1;
+        }
+
+        Ok(count)
+    }
+}
+
+#[test]
+fn test_fold_lines() {
+    let lines = fold_lines("a\r\nbc\n\nd".as_bytes(), Vec::new(), |mut lines, line| {
+        lines.push(line.to_string());
+        lines
+    });
+
+    assert_eq!(vec!["a", "bc", "", "d"], lines.unwrap());
+
+    let reader = io::BufReader::new(Repeat::new("xy\n", 100_000));
+    assert_eq!(
+        Some(200_000),
+        fold_lines(reader, 0, |total, line| total + line.len()).ok()
+    );
+}